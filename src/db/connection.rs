@@ -2,23 +2,82 @@
 //! (Ported to Turso from libsql using an LLM)
 
 use std::ops::Deref;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
 use tokio::sync::{Mutex, Notify, OnceCell, mpsc};
 use turso::Connection;
 use turso::sync::{Builder, Database};
 
+use super::migrations;
+
 const READ_POOL_SIZE: usize = 4;
 const BUSY_TIMEOUT: Duration = Duration::from_millis(5000);
-
-static DB: OnceCell<Database> = OnceCell::const_new();
+const DEFAULT_DATABASE_PATH: &str = "local.db";
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const PRUNE_BATCH_SIZE: u64 = 500;
+const DEFAULT_PULL_INTERVAL: Duration = Duration::from_secs(60);
+const PUSH_RETRY_MIN: Duration = Duration::from_secs(1);
+const PUSH_RETRY_MAX: Duration = Duration::from_secs(5 * 60);
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+// Pushes lagging further behind than this are logged as warnings.
+const PUSH_LAG_WARN: Duration = Duration::from_secs(60);
+
+static DB: OnceCell<Backend> = OnceCell::const_new();
 static WRITE_CONN: OnceCell<Connection> = OnceCell::const_new();
 static READ_POOL: OnceCell<ReadPool> = OnceCell::const_new();
 static PUSH_NOTIFY: Notify = Notify::const_new();
+static PULL_NOTIFY: Notify = Notify::const_new();
+// Total response_map rows pruned since startup.
+static PRUNED_ROWS: AtomicU64 = AtomicU64::new(0);
+// Only set in synced mode.
+static PENDING_PUSH: OnceLock<PendingPush> = OnceLock::new();
+// Total failed push attempts since startup.
+static PUSH_FAILURES: AtomicU64 = AtomicU64::new(0);
 
 pub static WRITE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
 
+/// Where the database lives, set with `DATABASE_MODE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatabaseMode {
+    /// A plain on-disk (or in-memory) database, with no remote.
+    Local,
+    /// A local replica kept in sync with a Turso remote.
+    #[default]
+    Synced,
+}
+
+impl FromStr for DatabaseMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "local" => Ok(Self::Local),
+            "synced" => Ok(Self::Synced),
+            _ => Err(anyhow::anyhow!("Unknown database mode: {}", s)),
+        }
+    }
+}
+
+enum Backend {
+    Local(turso::Database),
+    Synced(Database),
+}
+
+impl Backend {
+    async fn connect(&self) -> turso::Result<Connection> {
+        match self {
+            Self::Local(db) => db.connect(),
+            Self::Synced(db) => db.connect().await,
+        }
+    }
+}
+
 struct ReadPool {
     sender: mpsc::Sender<Connection>,
     receiver: Mutex<mpsc::Receiver<Connection>>,
@@ -59,7 +118,7 @@ pub async fn init() -> anyhow::Result<()> {
         .busy_timeout(BUSY_TIMEOUT)
         .context("Failed to set busy_timeout on write connection")?;
 
-    create_tables(&write_conn).await?;
+    migrations::run(&write_conn).await?;
 
     let (tx, rx) = mpsc::channel(READ_POOL_SIZE);
     for i in 0..READ_POOL_SIZE {
@@ -74,6 +133,7 @@ pub async fn init() -> anyhow::Result<()> {
             .expect("channel just created, cannot be closed");
     }
 
+    let synced = matches!(db, Backend::Synced(_));
     DB.set(db)
         .map_err(|_| anyhow::anyhow!("Database already initialized"))?;
     WRITE_CONN
@@ -86,8 +146,21 @@ pub async fn init() -> anyhow::Result<()> {
         })
         .map_err(|_| anyhow::anyhow!("Read pool already initialized"))?;
 
-    tokio::spawn(push_worker());
+    tokio::spawn(prune_worker());
+
+    // Nothing to push to or pull from in local mode.
+    if !synced {
+        return Ok(());
+    }
 
+    let pending = PENDING_PUSH.get_or_init(|| PendingPush::new(pending_marker_path()));
+    let left_over = pending.left_over();
+    tokio::spawn(push_worker());
+    if left_over {
+        tracing::info!("Pushing writes left unpushed by the last run");
+        request_push();
+    }
+    tokio::spawn(pull_worker());
     tokio::spawn(async {
         let mut delay = Duration::from_millis(250);
         let mut last_err: Option<anyhow::Error> = None;
@@ -148,53 +221,341 @@ pub async fn get_read_connection() -> anyhow::Result<ReadConnGuard> {
     })
 }
 
-/// Ask the background worker to push local writes to the remote.
+/// Ask the background worker to push local writes to the remote. Call this
+/// after the write has been committed.
 pub fn request_push() {
+    // Nothing to push in local mode.
+    let Some(pending) = PENDING_PUSH.get() else {
+        return;
+    };
+
+    pending.mark();
     PUSH_NOTIFY.notify_one();
 }
 
+/// How long the oldest write not yet on the remote has been waiting, if any.
+pub fn push_lag() -> Option<Duration> {
+    PENDING_PUSH.get()?.lag()
+}
+
+/// Pushes any writes still pending, giving up after `FLUSH_TIMEOUT`. Called on
+/// shutdown; anything left over is pushed on the next startup.
+pub async fn flush() {
+    let Some(pending) = PENDING_PUSH.get() else {
+        return;
+    };
+    if pending.lag().is_none() {
+        return;
+    }
+
+    tracing::info!("Flushing pending writes to remote");
+    match tokio::time::timeout(FLUSH_TIMEOUT, push_database()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => tracing::error!("Failed to flush pending writes: {:?}", e),
+        Err(_) => tracing::error!(
+            "Timed out flushing pending writes after {:?}, they will be pushed on next startup",
+            FLUSH_TIMEOUT
+        ),
+    }
+}
+
 pub async fn pull_database() -> anyhow::Result<()> {
+    let Some(db) = synced_db()? else {
+        return Ok(());
+    };
+
     let _guard = WRITE_LOCK.lock().await;
     tracing::debug!("Pulling changes from remote");
-    db()?
-        .pull()
+    db.pull()
         .await
         .context("Failed to pull changes from remote")?;
     tracing::debug!("Database pull completed");
+
+    PULL_NOTIFY.notify_one();
     Ok(())
 }
 
-fn db() -> anyhow::Result<&'static Database> {
-    DB.get()
-        .context("Database has not been initialized; call init_database() first")
+/// Waits until the next pull brings in changes from the remote. Never resolves
+/// in local mode.
+pub async fn wait_for_pull() {
+    PULL_NOTIFY.notified().await;
+}
+
+/// The synced database, or `None` in local mode.
+fn synced_db() -> anyhow::Result<Option<&'static Database>> {
+    let db = DB
+        .get()
+        .context("Database has not been initialized; call init_database() first")?;
+
+    match db {
+        Backend::Local(_) => Ok(None),
+        Backend::Synced(db) => Ok(Some(db)),
+    }
 }
 
+/// Pushes after every write, retrying failed pushes with exponential backoff
+/// until one goes through.
 async fn push_worker() {
     loop {
         PUSH_NOTIFY.notified().await;
-        if let Err(e) = push_database().await {
-            tracing::warn!("Failed to push database after write: {:?}", e);
+
+        let mut delay = PUSH_RETRY_MIN;
+        let mut attempt = 1;
+        while let Err(e) = push_database().await {
+            let failures = PUSH_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::warn!(
+                attempt,
+                failures,
+                lag = ?push_lag(),
+                "Failed to push database, retrying after {:?}: {:?}",
+                delay,
+                e
+            );
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(PUSH_RETRY_MAX);
+            attempt += 1;
+        }
+    }
+}
+
+/// Periodically pulls changes made by other instances sharing the remote, every
+/// `PULL_INTERVAL` seconds.
+async fn pull_worker() {
+    let period = std::env::var("PULL_INTERVAL")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map_or(DEFAULT_PULL_INTERVAL, Duration::from_secs);
+
+    tracing::info!("Pulling changes from remote every {:?}", period);
+
+    // The initial pull is already handled during init.
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = pull_database().await {
+            tracing::warn!("Failed to pull database: {:?}", e);
         }
     }
 }
 
 async fn push_database() -> anyhow::Result<()> {
+    let Some(db) = synced_db()? else {
+        return Ok(());
+    };
+
+    let Some(pending) = PENDING_PUSH.get() else {
+        return Ok(());
+    };
+
     let _guard = WRITE_LOCK.lock().await;
+    // Every write requested so far has been committed, since writes hold the lock.
+    let requests = pending.requests();
     tracing::debug!("Pushing local writes to remote");
-    db()?
-        .push()
+    db.push()
         .await
         .context("Failed to push local writes to remote")?;
+
+    if let Some(lag) = pending.clear(requests) {
+        if lag >= PUSH_LAG_WARN {
+            tracing::warn!(?lag, "Pushed local writes to remote");
+        } else {
+            tracing::debug!(?lag, "Pushed local writes to remote");
+        }
+    }
     Ok(())
 }
 
-async fn init_database_internal() -> anyhow::Result<Database> {
+/// Tracks writes that haven't reached the remote yet, mirrored to a marker file
+/// next to the database so they're pushed on startup if the bot stopped first.
+struct PendingPush {
+    marker: PathBuf,
+    state: std::sync::Mutex<PendingState>,
+}
+
+#[derive(Default)]
+struct PendingState {
+    // Bumped on every request, so a push only clears requests it covered.
+    requests: u64,
+    // When the oldest unpushed write was requested.
+    since: Option<Instant>,
+}
+
+impl PendingPush {
+    fn new(marker: PathBuf) -> Self {
+        Self {
+            marker,
+            state: Default::default(),
+        }
+    }
+
+    /// Whether the last run stopped with writes still pending.
+    fn left_over(&self) -> bool {
+        self.marker.exists()
+    }
+
+    fn mark(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.requests += 1;
+        if state.since.is_none() {
+            state.since = Some(Instant::now());
+            if let Err(e) = std::fs::write(&self.marker, b"") {
+                tracing::warn!("Failed to write {}: {:?}", self.marker.display(), e);
+            }
+        }
+    }
+
+    fn requests(&self) -> u64 {
+        self.state.lock().unwrap().requests
+    }
+
+    /// Clears the pending writes if nothing was requested since `requests` was
+    /// read, returning how long they waited.
+    fn clear(&self, requests: u64) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        if state.requests != requests {
+            return None;
+        }
+
+        let since = state.since.take()?;
+        if let Err(e) = std::fs::remove_file(&self.marker)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!("Failed to remove {}: {:?}", self.marker.display(), e);
+        }
+        Some(since.elapsed())
+    }
+
+    fn lag(&self) -> Option<Duration> {
+        Some(self.state.lock().unwrap().since?.elapsed())
+    }
+}
+
+/// Seconds since the unix epoch, as stored in `created_at` columns.
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Parses `RESPONSE_RETENTION_DAYS`, falling back to the default when it's
+/// unset or invalid. 0 is rejected too, as it would prune every entry.
+fn retention_days(value: Option<&str>) -> u64 {
+    let Some(value) = value else {
+        return DEFAULT_RETENTION_DAYS;
+    };
+
+    match value.parse::<u64>() {
+        Ok(days) if days > 0 => days,
+        _ => {
+            tracing::warn!(
+                "Invalid RESPONSE_RETENTION_DAYS {:?}, must be at least 1 day, using {}",
+                value,
+                DEFAULT_RETENTION_DAYS
+            );
+            DEFAULT_RETENTION_DAYS
+        }
+    }
+}
+
+/// Periodically deletes response_map rows older than `RESPONSE_RETENTION_DAYS`.
+async fn prune_worker() {
+    let retention_days = retention_days(std::env::var("RESPONSE_RETENTION_DAYS").ok().as_deref());
+    let retention = Duration::from_secs(retention_days * 24 * 60 * 60);
+
+    tracing::info!("Keeping response map entries for {} days", retention_days);
+
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let cutoff = unix_now() - retention.as_secs() as i64;
+        match prune_response_map(cutoff).await {
+            Ok(0) => tracing::debug!("No expired response map entries"),
+            Ok(pruned) => {
+                let total = PRUNED_ROWS.fetch_add(pruned, Ordering::Relaxed) + pruned;
+                tracing::info!(pruned, total, "Pruned expired response map entries");
+                request_push();
+            }
+            Err(e) => tracing::warn!("Failed to prune response map: {:?}", e),
+        }
+    }
+}
+
+/// Deletes every row created before the cutoff, one batch at a time so other
+/// writes don't wait on the lock for long.
+async fn prune_response_map(cutoff: i64) -> anyhow::Result<u64> {
+    let mut pruned = 0;
+
+    loop {
+        let deleted = {
+            let _guard = WRITE_LOCK.lock().await;
+            prune_batch(get_write_connection()?, cutoff).await?
+        };
+        pruned += deleted;
+
+        if deleted < PRUNE_BATCH_SIZE {
+            return Ok(pruned);
+        }
+    }
+}
+
+async fn prune_batch(conn: &Connection, cutoff: i64) -> anyhow::Result<u64> {
+    let sql = r#"
+        DELETE FROM response_map
+        WHERE user_message_id IN (
+            SELECT user_message_id FROM response_map
+            WHERE created_at < ?1
+            LIMIT ?2
+        )
+    "#;
+
+    conn.execute(sql, (cutoff, PRUNE_BATCH_SIZE as i64))
+        .await
+        .context("Failed to prune response map")
+}
+
+async fn init_database_internal() -> anyhow::Result<Backend> {
+    let mode = match std::env::var("DATABASE_MODE") {
+        Ok(mode) => mode.parse::<DatabaseMode>()?,
+        Err(_) => DatabaseMode::default(),
+    };
+    let path = database_path();
+
+    match mode {
+        DatabaseMode::Local => open_local(&path).await,
+        DatabaseMode::Synced => open_synced(&path).await,
+    }
+}
+
+fn database_path() -> String {
+    std::env::var("DATABASE_PATH").unwrap_or_else(|_| DEFAULT_DATABASE_PATH.to_string())
+}
+
+/// Marks the synced database as having writes not yet pushed.
+fn pending_marker_path() -> PathBuf {
+    PathBuf::from(format!("{}.push-pending", database_path()))
+}
+
+/// Opens a database at the path, which can also be `:memory:`.
+async fn open_local(path: &str) -> anyhow::Result<Backend> {
+    tracing::info!("Initializing local database at {}", path);
+
+    let db = turso::Builder::new_local(path)
+        .build()
+        .await
+        .context("Failed to open local database")?;
+
+    Ok(Backend::Local(db))
+}
+
+async fn open_synced(path: &str) -> anyhow::Result<Backend> {
     let url = std::env::var("TURSO_DATABASE_URL").context("TURSO_DATABASE_URL must be set")?;
     let auth_token = std::env::var("TURSO_AUTH_TOKEN").context("TURSO_AUTH_TOKEN must be set")?;
 
     tracing::info!("Initializing Turso synced database");
 
-    let db = Builder::new_remote("local.db")
+    let db = Builder::new_remote(path)
         .with_remote_url(&url)
         .with_auth_token(&auth_token)
         .bootstrap_if_empty(true)
@@ -202,47 +563,104 @@ async fn init_database_internal() -> anyhow::Result<Database> {
         .await
         .context("Failed to build database connection")?;
 
-    Ok(db)
+    Ok(Backend::Synced(db))
 }
 
-async fn create_tables(conn: &Connection) -> anyhow::Result<()> {
-    tracing::debug!("Ensuring database schema exists");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::tests::memory_connection;
 
-    let create_server_configs_table = r#"
-        CREATE TABLE IF NOT EXISTS server_configs (
-            guild_id INTEGER PRIMARY KEY,
-            sanitizer_mode INTEGER NOT NULL DEFAULT 0,
-            delete_permission INTEGER NOT NULL DEFAULT 0,
-            hide_original_embed BOOLEAN NOT NULL DEFAULT true
-        )
-    "#;
+    async fn count_rows(conn: &Connection) -> i64 {
+        let mut rows = conn
+            .query("SELECT COUNT(*) FROM response_map", ())
+            .await
+            .unwrap();
+        rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap()
+    }
 
-    conn.execute(create_server_configs_table, ())
-        .await
-        .context("Failed to create server_configs table")?;
-
-    let create_response_map_table = r#"
-        CREATE TABLE IF NOT EXISTS response_map (
-            user_message_id INTEGER PRIMARY KEY,
-            bot_message_id INTEGER NOT NULL UNIQUE,
-            guild_id INTEGER,
-            channel_id INTEGER NOT NULL
-        )
-        "#;
+    #[test]
+    fn test_retention_days() {
+        assert_eq!(retention_days(None), DEFAULT_RETENTION_DAYS);
+        assert_eq!(retention_days(Some("7")), 7);
+        assert_eq!(retention_days(Some("0")), DEFAULT_RETENTION_DAYS);
+        assert_eq!(retention_days(Some("week")), DEFAULT_RETENTION_DAYS);
+    }
 
-    conn.execute(create_response_map_table, ())
-        .await
-        .context("Failed to create response_map table")?;
+    #[test]
+    fn test_database_mode_from_str() {
+        assert_eq!(
+            "local".parse::<DatabaseMode>().unwrap(),
+            DatabaseMode::Local
+        );
+        assert_eq!(
+            "Synced".parse::<DatabaseMode>().unwrap(),
+            DatabaseMode::Synced
+        );
+        assert!("remote".parse::<DatabaseMode>().is_err());
+    }
 
-    let create_index = r#"
-            CREATE INDEX IF NOT EXISTS idx_response_map_location
-            ON response_map (guild_id, channel_id)
-        "#;
+    #[tokio::test]
+    async fn test_local_memory_database() {
+        let db = open_local(":memory:").await.unwrap();
+        let write_conn = db.connect().await.unwrap();
+        let read_conn = db.connect().await.unwrap();
 
-    conn.execute(create_index, ())
-        .await
-        .context("Failed to create index for response_map")?;
+        migrations::run(&write_conn).await.unwrap();
+        write_conn
+            .execute("INSERT INTO response_map VALUES (1, 2, 3, 4, 5, 0)", ())
+            .await
+            .unwrap();
 
-    tracing::debug!("Database schema set.");
-    Ok(())
+        assert_eq!(count_rows(&read_conn).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_prune_batch_deletes_expired_rows() {
+        let conn = memory_connection().await;
+        migrations::run(&conn).await.unwrap();
+
+        for id in 0..(PRUNE_BATCH_SIZE as i64 + 10) {
+            conn.execute(
+                "INSERT INTO response_map VALUES (?1, ?2, 1, 1, ?3, 0)",
+                (id, -id - 1, 100),
+            )
+            .await
+            .unwrap();
+        }
+        conn.execute("INSERT INTO response_map VALUES (-1, 1, 1, 1, 300, 0)", ())
+            .await
+            .unwrap();
+
+        assert_eq!(prune_batch(&conn, 200).await.unwrap(), PRUNE_BATCH_SIZE);
+        assert_eq!(prune_batch(&conn, 200).await.unwrap(), 10);
+        assert_eq!(prune_batch(&conn, 200).await.unwrap(), 0);
+        assert_eq!(count_rows(&conn).await, 1);
+    }
+
+    #[test]
+    fn test_pending_push_survives_restart_until_cleared() {
+        let marker = std::env::temp_dir().join(format!("pending-push-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+
+        let pending = PendingPush::new(marker.clone());
+        assert!(!pending.left_over());
+        assert!(pending.lag().is_none());
+
+        pending.mark();
+        let requests = pending.requests();
+        pending.mark();
+        assert!(pending.lag().is_some());
+
+        // A restarted process sees the marker.
+        assert!(PendingPush::new(marker.clone()).left_over());
+
+        // The write requested mid-push keeps it pending.
+        assert_eq!(pending.clear(requests), None);
+        assert!(pending.left_over());
+
+        assert!(pending.clear(pending.requests()).is_some());
+        assert!(!pending.left_over());
+        assert!(pending.lag().is_none());
+    }
 }
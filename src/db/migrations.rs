@@ -0,0 +1,413 @@
+//! Versioned schema migrations, applied in order when the database is initialized.
+//!
+//! Each migration is embedded SQL that runs in its own transaction, together with
+//! recording its version in `schema_migrations`. Applied migrations must never be
+//! edited, changes to the schema always go in a new file.
+
+use anyhow::Context;
+use turso::Connection;
+
+use super::connection::unix_now;
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+    /// The `(table, column)` the migration adds. Databases set up before
+    /// versioning may already have it, the migration is then only recorded.
+    adds_column: Option<(&'static str, &'static str)>,
+}
+
+/// Every migration, ordered by version.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("migrations/0001_initial.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 2,
+        name: "replacement_domains",
+        sql: include_str!("migrations/0002_replacement_domains.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 3,
+        name: "response_map_created_at",
+        sql: include_str!("migrations/0003_response_map_created_at.sql"),
+        adds_column: Some(("response_map", "created_at")),
+    },
+    Migration {
+        version: 4,
+        name: "server_configs_updated_at",
+        sql: include_str!("migrations/0004_server_configs_updated_at.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 5,
+        name: "channel_configs",
+        sql: include_str!("migrations/0005_channel_configs.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 6,
+        name: "server_configs_disabled_platforms",
+        sql: include_str!("migrations/0006_server_configs_disabled_platforms.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 7,
+        name: "guild_roles",
+        sql: include_str!("migrations/0007_guild_roles.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 8,
+        name: "user_opt_outs",
+        sql: include_str!("migrations/0008_user_opt_outs.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 9,
+        name: "response_map_reposted",
+        sql: include_str!("migrations/0009_response_map_reposted.sql"),
+        adds_column: None,
+    },
+    Migration {
+        version: 10,
+        name: "server_configs_reply_template",
+        sql: include_str!("migrations/0010_server_configs_reply_template.sql"),
+        adds_column: None,
+    },
+];
+
+/// Brings the schema up to date by applying every migration not yet recorded.
+pub async fn run(conn: &Connection) -> anyhow::Result<()> {
+    apply(conn, MIGRATIONS).await
+}
+
+async fn apply(conn: &Connection, migrations: &[Migration]) -> anyhow::Result<()> {
+    tracing::debug!("Ensuring database schema is up to date");
+
+    let create_schema_migrations_table = r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )
+    "#;
+
+    conn.execute(create_schema_migrations_table, ())
+        .await
+        .context("Failed to create schema_migrations table")?;
+
+    let current = current_version(conn).await?;
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let already_applied = match migration.adds_column {
+            Some((table, column)) => has_column(conn, table, column).await?,
+            None => false,
+        };
+
+        let tx = conn
+            .unchecked_transaction()
+            .await
+            .context("Failed to begin migration transaction")?;
+
+        if already_applied {
+            tracing::info!(
+                "Recording database migration {} ({}), the schema already has it",
+                migration.version,
+                migration.name
+            );
+        } else {
+            tracing::info!(
+                "Applying database migration {} ({})",
+                migration.version,
+                migration.name
+            );
+
+            tx.execute_batch(migration.sql).await.with_context(|| {
+                format!(
+                    "Failed to apply migration {} ({})",
+                    migration.version, migration.name
+                )
+            })?;
+        }
+
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            (migration.version, migration.name, unix_now()),
+        )
+        .await
+        .context("Failed to record migration")?;
+
+        tx.commit()
+            .await
+            .with_context(|| format!("Failed to commit migration {}", migration.version))?;
+    }
+
+    tracing::debug!("Database schema set.");
+    Ok(())
+}
+
+async fn current_version(conn: &Connection) -> anyhow::Result<i64> {
+    let mut rows = conn
+        .query(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+            (),
+        )
+        .await
+        .context("Failed to read schema version")?;
+
+    let row = rows
+        .next()
+        .await
+        .context("Failed to fetch row")?
+        .context("Schema version query returned no rows")?;
+
+    Ok(row.get::<i64>(0)?)
+}
+
+async fn has_column(conn: &Connection, table: &str, column: &str) -> anyhow::Result<bool> {
+    let mut rows = conn
+        .query(format!("PRAGMA table_info({table})"), ())
+        .await
+        .with_context(|| format!("Failed to read columns of {table}"))?;
+
+    while let Some(row) = rows.next().await.context("Failed to fetch row")? {
+        if row.get::<String>(1)? == column {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) async fn memory_connection() -> Connection {
+        turso::Builder::new_local(":memory:")
+            .build()
+            .await
+            .unwrap()
+            .connect()
+            .unwrap()
+    }
+
+    async fn row_count(conn: &Connection, sql: &str) -> i64 {
+        let mut rows = conn.query(sql, ()).await.unwrap();
+        rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap()
+    }
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for (idx, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, idx as i64 + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fresh_database() {
+        let conn = memory_connection().await;
+        run(&conn).await.unwrap();
+
+        assert_eq!(
+            current_version(&conn).await.unwrap(),
+            MIGRATIONS.len() as i64
+        );
+        assert!(
+            has_column(&conn, "response_map", "created_at")
+                .await
+                .unwrap()
+        );
+        assert!(
+            has_column(&conn, "server_configs", "updated_at")
+                .await
+                .unwrap()
+        );
+        assert!(
+            has_column(&conn, "server_configs", "version")
+                .await
+                .unwrap()
+        );
+        assert!(
+            has_column(&conn, "channel_configs", "sanitizer_mode")
+                .await
+                .unwrap()
+        );
+        assert!(
+            has_column(&conn, "server_configs", "disabled_platforms")
+                .await
+                .unwrap()
+        );
+        assert!(has_column(&conn, "guild_roles", "role_id").await.unwrap());
+        assert!(has_column(&conn, "user_opt_outs", "user_id").await.unwrap());
+        assert!(has_column(&conn, "response_map", "reposted").await.unwrap());
+        assert!(
+            has_column(&conn, "server_configs", "reply_template")
+                .await
+                .unwrap()
+        );
+
+        // Running again is a no-op.
+        run(&conn).await.unwrap();
+        assert_eq!(
+            current_version(&conn).await.unwrap(),
+            MIGRATIONS.len() as i64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upgrades_unversioned_database() {
+        let conn = memory_connection().await;
+
+        // A database set up before migrations existed.
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS server_configs (
+                guild_id INTEGER PRIMARY KEY,
+                sanitizer_mode INTEGER NOT NULL DEFAULT 0,
+                delete_permission INTEGER NOT NULL DEFAULT 0,
+                hide_original_embed BOOLEAN NOT NULL DEFAULT true
+            );
+            CREATE TABLE IF NOT EXISTS response_map (
+                user_message_id INTEGER PRIMARY KEY,
+                bot_message_id INTEGER NOT NULL UNIQUE,
+                guild_id INTEGER,
+                channel_id INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_response_map_location
+            ON response_map (guild_id, channel_id);
+            "#,
+        )
+        .await
+        .unwrap();
+        conn.execute("INSERT INTO server_configs VALUES (1, 2, 1, false)", ())
+            .await
+            .unwrap();
+        conn.execute("INSERT INTO response_map VALUES (10, 20, 1, 30)", ())
+            .await
+            .unwrap();
+
+        run(&conn).await.unwrap();
+
+        assert_eq!(
+            current_version(&conn).await.unwrap(),
+            MIGRATIONS.len() as i64
+        );
+
+        let mut rows = conn
+            .query(
+                "SELECT sanitizer_mode FROM server_configs WHERE guild_id = 1",
+                (),
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<i64>(0).unwrap(), 2);
+        drop(rows);
+
+        let mut rows = conn
+            .query(
+                "SELECT bot_message_id, created_at FROM response_map WHERE user_message_id = 10",
+                (),
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<i64>(0).unwrap(), 20);
+        assert!(row.get::<i64>(1).unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_upgrades_database_with_retention_schema() {
+        let conn = memory_connection().await;
+
+        // A database set up before migrations existed, but after replacement
+        // domains and retention were added.
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS server_configs (
+                guild_id INTEGER PRIMARY KEY,
+                sanitizer_mode INTEGER NOT NULL DEFAULT 0,
+                delete_permission INTEGER NOT NULL DEFAULT 0,
+                hide_original_embed BOOLEAN NOT NULL DEFAULT true
+            );
+            CREATE TABLE IF NOT EXISTS replacement_domains (
+                guild_id INTEGER NOT NULL,
+                platform_id TEXT NOT NULL,
+                domain TEXT NOT NULL,
+                PRIMARY KEY (guild_id, platform_id)
+            );
+            CREATE TABLE IF NOT EXISTS response_map (
+                user_message_id INTEGER PRIMARY KEY,
+                bot_message_id INTEGER NOT NULL UNIQUE,
+                guild_id INTEGER,
+                channel_id INTEGER NOT NULL,
+                created_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS idx_response_map_created_at
+            ON response_map (created_at);
+            CREATE INDEX IF NOT EXISTS idx_response_map_location
+            ON response_map (guild_id, channel_id);
+            "#,
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "INSERT INTO replacement_domains VALUES (1, 'x', 'fixupx.com')",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute("INSERT INTO response_map VALUES (10, 20, 1, 30, 12345)", ())
+            .await
+            .unwrap();
+
+        run(&conn).await.unwrap();
+
+        assert_eq!(
+            current_version(&conn).await.unwrap(),
+            MIGRATIONS.len() as i64
+        );
+        assert_eq!(
+            row_count(&conn, "SELECT COUNT(*) FROM replacement_domains").await,
+            1
+        );
+        // Rows keep their original age.
+        assert_eq!(
+            row_count(
+                &conn,
+                "SELECT created_at FROM response_map WHERE user_message_id = 10"
+            )
+            .await,
+            12345
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        let conn = memory_connection().await;
+        let migrations = [Migration {
+            version: 1,
+            name: "broken",
+            sql: "CREATE TABLE partial (id INTEGER); INSERT INTO missing VALUES (1);",
+            adds_column: None,
+        }];
+
+        assert!(apply(&conn, &migrations).await.is_err());
+        assert_eq!(current_version(&conn).await.unwrap(), 0);
+        assert_eq!(
+            row_count(
+                &conn,
+                "SELECT COUNT(*) FROM sqlite_schema WHERE name = 'partial'"
+            )
+            .await,
+            0
+        );
+    }
+}
@@ -0,0 +1,17 @@
+-- The schema from before migrations existed, so existing databases adopt it as is.
+CREATE TABLE IF NOT EXISTS server_configs (
+    guild_id INTEGER PRIMARY KEY,
+    sanitizer_mode INTEGER NOT NULL DEFAULT 0,
+    delete_permission INTEGER NOT NULL DEFAULT 0,
+    hide_original_embed BOOLEAN NOT NULL DEFAULT true
+);
+
+CREATE TABLE IF NOT EXISTS response_map (
+    user_message_id INTEGER PRIMARY KEY,
+    bot_message_id INTEGER NOT NULL UNIQUE,
+    guild_id INTEGER,
+    channel_id INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_response_map_location
+ON response_map (guild_id, channel_id);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS replacement_domains (
+    guild_id INTEGER NOT NULL,
+    platform_id TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    PRIMARY KEY (guild_id, platform_id)
+);
@@ -0,0 +1,7 @@
+-- Existing rows are kept for a full retention window from the upgrade.
+ALTER TABLE response_map ADD COLUMN created_at INTEGER;
+
+UPDATE response_map SET created_at = unixepoch() WHERE created_at IS NULL;
+
+CREATE INDEX idx_response_map_created_at
+ON response_map (created_at);
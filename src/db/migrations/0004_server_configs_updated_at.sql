@@ -0,0 +1,8 @@
+-- Lets instances sharing the database find configs changed by the others. The
+-- version tells apart saves stamped with the same second.
+ALTER TABLE server_configs ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE server_configs ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_server_configs_updated_at
+ON server_configs (updated_at);
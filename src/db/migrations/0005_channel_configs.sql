@@ -0,0 +1,9 @@
+-- Per-channel overrides of the server config, NULL meaning the server's setting applies.
+CREATE TABLE channel_configs (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    sanitizer_mode INTEGER,
+    delete_permission INTEGER,
+    hide_original_embed BOOLEAN,
+    PRIMARY KEY (guild_id, channel_id)
+);
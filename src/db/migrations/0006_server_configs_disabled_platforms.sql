@@ -0,0 +1,2 @@
+-- Comma separated ids of the platforms a guild turned off.
+ALTER TABLE server_configs ADD COLUMN disabled_platforms TEXT NOT NULL DEFAULT '';
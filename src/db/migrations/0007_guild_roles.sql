@@ -0,0 +1,8 @@
+-- Roles with a part in a guild's settings, `list` saying which:
+-- 0 = allowed to trigger the sanitizer, 1 = exempt from it, 2 = moderators.
+CREATE TABLE guild_roles (
+    guild_id INTEGER NOT NULL,
+    list INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, list, role_id)
+);
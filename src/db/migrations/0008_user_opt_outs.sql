@@ -0,0 +1,6 @@
+-- Users who don't want the bot replying to their messages, guild_id 0 meaning everywhere.
+CREATE TABLE user_opt_outs (
+    user_id INTEGER NOT NULL,
+    guild_id INTEGER NOT NULL,
+    PRIMARY KEY (user_id, guild_id)
+);
@@ -0,0 +1,2 @@
+-- Set when the bot's message is a webhook repost replacing the user's message.
+ALTER TABLE response_map ADD COLUMN reposted BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- The guild's format for fixed links, NULL for each platform's default.
+ALTER TABLE server_configs ADD COLUMN reply_template TEXT;
@@ -1,5 +1,12 @@
 mod connection;
+mod migrations;
 mod operations;
+mod store;
 
-pub use connection::init;
-pub use operations::{MessageAuthor, ResponseMap, ServerConfig};
+pub use connection::{flush, init, wait_for_pull};
+#[cfg(test)]
+pub use operations::ChannelConfig;
+pub use operations::{ConfigChange, MessageAuthor, OptOut, ResponseMap, ServerConfig};
+#[cfg(test)]
+pub use store::MemoryStore;
+pub use store::{ConfigStore, OptOutStore, ResponseStore, TursoStore};
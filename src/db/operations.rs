@@ -1,45 +1,183 @@
 //! All the database operations are stored in this module.
 //! (Ported to Turso from libsql using an LLM)
 
+use std::collections::{HashMap, HashSet};
+
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 use twilight_model::channel::Message;
 use twilight_model::id::Id;
-use twilight_model::id::marker::MessageMarker;
+use twilight_model::id::marker::{MessageMarker, RoleMarker};
 
-use super::connection::{WRITE_LOCK, get_read_connection, get_write_connection, request_push};
+use super::connection::{
+    WRITE_LOCK, get_read_connection, get_write_connection, request_push, unix_now,
+};
 use crate::discord::models::{DeletePermission, SanitizerMode};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// Values of `guild_roles.list`.
+const ALLOWED_ROLES: i32 = 0;
+const EXEMPT_ROLES: i32 = 1;
+const MODERATOR_ROLES: i32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ServerConfig {
     pub guild_id: u64,
     pub sanitizer_mode: SanitizerMode,
     pub delete_permission: DeletePermission,
     pub hide_original_embed: bool,
+    /// Platform id -> fixer domain picked by the guild instead of the default.
+    pub replacement_domains: HashMap<String, String>,
+    /// Channel id -> settings overridden in that channel.
+    pub channel_overrides: HashMap<u64, ChannelConfig>,
+    /// Ids of the platforms the guild turned off. Kept this way round so
+    /// platforms added later start out enabled.
+    pub disabled_platforms: HashSet<String>,
+    /// Roles whose members get their links fixed, everyone's when empty.
+    pub allowed_roles: HashSet<u64>,
+    /// Roles whose members never get their links fixed.
+    pub exempt_roles: HashSet<u64>,
+    /// Roles the delete button treats as moderators, on top of Manage Messages.
+    pub moderator_roles: HashSet<u64>,
+    /// Format of the fixed links, see `Platform::label`. `None` keeps each
+    /// platform's own format.
+    pub reply_template: Option<String>,
+}
+
+/// Settings overridden for a channel and the threads in it. `None` means the
+/// server's setting applies.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChannelConfig {
+    pub sanitizer_mode: Option<SanitizerMode>,
+    pub delete_permission: Option<DeletePermission>,
+    pub hide_original_embed: Option<bool>,
+}
+
+impl ChannelConfig {
+    /// Whether nothing is overridden.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 impl ServerConfig {
-    pub async fn save(&self) -> anyhow::Result<()> {
+    pub(super) async fn save(&self) -> anyhow::Result<()> {
         let sql = r#"
             INSERT OR REPLACE INTO server_configs
-            (guild_id, sanitizer_mode, delete_permission, hide_original_embed)
-            VALUES (?1, ?2, ?3, ?4)
+            (guild_id, sanitizer_mode, delete_permission, hide_original_embed, updated_at,
+             disabled_platforms, reply_template, version)
+            VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7,
+                COALESCE((SELECT version FROM server_configs WHERE guild_id = ?1), 0) + 1
+            )
         "#;
 
+        let mut disabled_platforms = self
+            .disabled_platforms
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        disabled_platforms.sort_unstable();
+        let disabled_platforms = disabled_platforms.join(",");
+
         {
             let _guard = WRITE_LOCK.lock().await;
             let conn = get_write_connection()?;
-            conn.execute(
+            let tx = conn
+                .unchecked_transaction()
+                .await
+                .context("Failed to begin server config transaction")?;
+
+            tx.execute(
                 sql,
                 (
                     self.guild_id as i64,
                     self.sanitizer_mode as i32,
                     self.delete_permission as i32,
                     self.hide_original_embed,
+                    unix_now(),
+                    disabled_platforms,
+                    self.reply_template.as_deref(),
                 ),
             )
             .await
             .context("Failed to save server config")?;
+
+            tx.execute(
+                "DELETE FROM replacement_domains WHERE guild_id = ?",
+                [self.guild_id as i64],
+            )
+            .await
+            .context("Failed to clear replacement domains")?;
+
+            for (platform_id, domain) in &self.replacement_domains {
+                tx.execute(
+                    r#"
+                    INSERT INTO replacement_domains (guild_id, platform_id, domain)
+                    VALUES (?1, ?2, ?3)
+                    "#,
+                    (self.guild_id as i64, platform_id.as_str(), domain.as_str()),
+                )
+                .await
+                .context("Failed to save replacement domain")?;
+            }
+
+            tx.execute(
+                "DELETE FROM channel_configs WHERE guild_id = ?",
+                [self.guild_id as i64],
+            )
+            .await
+            .context("Failed to clear channel configs")?;
+
+            for (channel_id, overrides) in &self.channel_overrides {
+                if overrides.is_empty() {
+                    continue;
+                }
+
+                tx.execute(
+                    r#"
+                    INSERT INTO channel_configs
+                    (guild_id, channel_id, sanitizer_mode, delete_permission, hide_original_embed)
+                    VALUES (?1, ?2, ?3, ?4, ?5)
+                    "#,
+                    (
+                        self.guild_id as i64,
+                        *channel_id as i64,
+                        overrides.sanitizer_mode.map(|mode| mode as i32),
+                        overrides
+                            .delete_permission
+                            .map(|permission| permission as i32),
+                        overrides.hide_original_embed,
+                    ),
+                )
+                .await
+                .context("Failed to save channel config")?;
+            }
+
+            tx.execute(
+                "DELETE FROM guild_roles WHERE guild_id = ?",
+                [self.guild_id as i64],
+            )
+            .await
+            .context("Failed to clear guild roles")?;
+
+            for (list, roles) in [
+                (ALLOWED_ROLES, &self.allowed_roles),
+                (EXEMPT_ROLES, &self.exempt_roles),
+                (MODERATOR_ROLES, &self.moderator_roles),
+            ] {
+                for role_id in roles {
+                    tx.execute(
+                        "INSERT INTO guild_roles (guild_id, list, role_id) VALUES (?1, ?2, ?3)",
+                        (self.guild_id as i64, list, *role_id as i64),
+                    )
+                    .await
+                    .context("Failed to save guild role")?;
+                }
+            }
+
+            tx.commit()
+                .await
+                .context("Failed to commit server config")?;
         }
 
         tracing::debug!("Saved config for guild {}", self.guild_id);
@@ -50,31 +188,71 @@ impl ServerConfig {
         Ok(())
     }
 
-    pub async fn get_or_default(guild_id: u64) -> anyhow::Result<Self> {
-        match Self::get(guild_id).await? {
-            Some(config) => Ok(config),
-            None => {
-                tracing::debug!("Using default config for guild ({})", guild_id);
-                Ok(Self::new(guild_id))
-            }
-        }
-    }
-
-    fn new(guild_id: u64) -> Self {
+    /// The config used for guilds that never changed their settings.
+    pub fn new(guild_id: u64) -> Self {
         Self {
             guild_id,
             sanitizer_mode: SanitizerMode::default(),
             delete_permission: DeletePermission::default(),
             hide_original_embed: true,
+            replacement_domains: HashMap::new(),
+            channel_overrides: HashMap::new(),
+            disabled_platforms: HashSet::new(),
+            allowed_roles: HashSet::new(),
+            exempt_roles: HashSet::new(),
+            moderator_roles: HashSet::new(),
+            reply_template: None,
         }
     }
 
+    /// Whether a member with the roles gets their links fixed.
+    pub fn is_sanitized_for(&self, roles: &[Id<RoleMarker>]) -> bool {
+        let has_any = |list: &HashSet<u64>| roles.iter().any(|role| list.contains(&role.get()));
+
+        (self.allowed_roles.is_empty() || has_any(&self.allowed_roles))
+            && !has_any(&self.exempt_roles)
+    }
+
+    /// Whether a member with the roles has one of the moderator roles.
+    pub fn is_moderator(&self, roles: &[Id<RoleMarker>]) -> bool {
+        roles
+            .iter()
+            .any(|role| self.moderator_roles.contains(&role.get()))
+    }
+
+    pub fn is_platform_enabled(&self, platform_id: &str) -> bool {
+        !self.disabled_platforms.contains(platform_id)
+    }
+
+    /// The config that applies in a channel, taking its overrides into account.
+    /// Threads inherit the overrides of their parent channel.
+    pub fn for_channel(&self, channel_id: u64, parent_id: Option<u64>) -> Self {
+        let mut config = self.clone();
+        let overrides = self
+            .channel_overrides
+            .get(&channel_id)
+            .or_else(|| parent_id.and_then(|id| self.channel_overrides.get(&id)));
+
+        if let Some(overrides) = overrides {
+            config.sanitizer_mode = overrides.sanitizer_mode.unwrap_or(self.sanitizer_mode);
+            config.delete_permission = overrides
+                .delete_permission
+                .unwrap_or(self.delete_permission);
+            config.hide_original_embed = overrides
+                .hide_original_embed
+                .unwrap_or(self.hide_original_embed);
+        }
+
+        config
+    }
+
     // Uses the read pool - no lock needed.
-    async fn get(guild_id: u64) -> anyhow::Result<Option<Self>> {
+    pub(super) async fn get(guild_id: u64) -> anyhow::Result<Option<Self>> {
         let conn = get_read_connection().await?;
 
         let sql = r#"
-            SELECT guild_id, sanitizer_mode, delete_permission, hide_original_embed
+            SELECT guild_id, sanitizer_mode, delete_permission, hide_original_embed,
+                   disabled_platforms, reply_template
             FROM server_configs
             WHERE guild_id = ?
         "#;
@@ -91,14 +269,120 @@ impl ServerConfig {
 
         tracing::debug!(%guild_id, "Found existing config for guild");
 
+        let (sanitizer_mode, delete_permission, hide_original_embed) = (
+            row.get::<i32>(1)?.into(),
+            row.get::<i32>(2)?.into(),
+            row.get::<bool>(3)?,
+        );
+        let disabled_platforms = row
+            .get::<String>(4)?
+            .split(',')
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .collect();
+        let reply_template = row.get::<Option<String>>(5)?;
+        drop(rows);
+
+        let mut rows = conn
+            .query(
+                "SELECT platform_id, domain FROM replacement_domains WHERE guild_id = ?",
+                [guild_id as i64],
+            )
+            .await
+            .context("Failed to execute SELECT query for replacement domains")?;
+
+        let mut replacement_domains = HashMap::new();
+        while let Some(row) = rows.next().await.context("Failed to fetch row")? {
+            replacement_domains.insert(row.get::<String>(0)?, row.get::<String>(1)?);
+        }
+        drop(rows);
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT channel_id, sanitizer_mode, delete_permission, hide_original_embed
+                FROM channel_configs
+                WHERE guild_id = ?
+                "#,
+                [guild_id as i64],
+            )
+            .await
+            .context("Failed to execute SELECT query for channel configs")?;
+
+        let mut channel_overrides = HashMap::new();
+        while let Some(row) = rows.next().await.context("Failed to fetch row")? {
+            let overrides = ChannelConfig {
+                sanitizer_mode: row.get::<Option<i32>>(1)?.map(Into::into),
+                delete_permission: row.get::<Option<i32>>(2)?.map(Into::into),
+                hide_original_embed: row.get::<Option<bool>>(3)?,
+            };
+            channel_overrides.insert(row.get::<i64>(0)? as u64, overrides);
+        }
+        drop(rows);
+
+        let mut rows = conn
+            .query(
+                "SELECT list, role_id FROM guild_roles WHERE guild_id = ?",
+                [guild_id as i64],
+            )
+            .await
+            .context("Failed to execute SELECT query for guild roles")?;
+
+        let (mut allowed_roles, mut exempt_roles, mut moderator_roles) =
+            (HashSet::new(), HashSet::new(), HashSet::new());
+        while let Some(row) = rows.next().await.context("Failed to fetch row")? {
+            let role_id = row.get::<i64>(1)? as u64;
+            match row.get::<i32>(0)? {
+                ALLOWED_ROLES => allowed_roles.insert(role_id),
+                EXEMPT_ROLES => exempt_roles.insert(role_id),
+                MODERATOR_ROLES => moderator_roles.insert(role_id),
+                list => {
+                    tracing::warn!(%guild_id, list, "Unknown guild role list");
+                    continue;
+                }
+            };
+        }
+
         Ok(Some(Self {
             guild_id,
-            sanitizer_mode: row.get::<i32>(1)?.into(),
-            delete_permission: row.get::<i32>(2)?.into(),
-            hide_original_embed: row.get::<bool>(3)?,
+            sanitizer_mode,
+            delete_permission,
+            hide_original_embed,
+            replacement_domains,
+            channel_overrides,
+            disabled_platforms,
+            allowed_roles,
+            exempt_roles,
+            moderator_roles,
+            reply_template,
         }))
     }
 
+    /// Returns the latest save of every config saved at or after `since`.
+    // Uses the read pool - no lock needed.
+    pub(super) async fn changed_since(since: i64) -> anyhow::Result<Vec<ConfigChange>> {
+        let conn = get_read_connection().await?;
+
+        let mut rows = conn
+            .query(
+                "SELECT guild_id, updated_at, version FROM server_configs WHERE updated_at >= ?",
+                [since],
+            )
+            .await
+            .context("Failed to execute SELECT query for changed configs")?;
+
+        let mut changed = Vec::new();
+        while let Some(row) = rows.next().await.context("Failed to fetch row")? {
+            changed.push(ConfigChange {
+                guild_id: row.get::<i64>(0)? as u64,
+                updated_at: row.get::<i64>(1)?,
+                version: row.get::<i64>(2)?,
+            });
+        }
+
+        Ok(changed)
+    }
+
     // pub async fn delete(guild_id: u64) -> anyhow::Result<()> {
     //    let conn = get_connection()?;
 
@@ -113,6 +397,88 @@ impl ServerConfig {
     // }
 }
 
+/// The latest save of a guild's config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigChange {
+    pub guild_id: u64,
+    /// When it was saved, to the second.
+    pub updated_at: i64,
+    /// Bumped on every save, telling apart saves within the same second.
+    pub version: i64,
+}
+
+/// A user who doesn't want the bot replying to their messages, in one guild or
+/// everywhere.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct OptOut {
+    pub user_id: u64,
+    /// `None` for everywhere.
+    pub guild_id: Option<u64>,
+}
+
+impl OptOut {
+    pub(super) async fn save(&self) -> anyhow::Result<()> {
+        let sql = "INSERT OR REPLACE INTO user_opt_outs (user_id, guild_id) VALUES (?1, ?2)";
+
+        {
+            let _guard = WRITE_LOCK.lock().await;
+            let conn = get_write_connection()?;
+            conn.execute(
+                sql,
+                (self.user_id as i64, self.guild_id.unwrap_or(0) as i64),
+            )
+            .await
+            .context("Failed to save opt-out")?;
+        }
+
+        tracing::debug!("Saved opt-out: {:?}", self);
+
+        request_push();
+
+        Ok(())
+    }
+
+    pub(super) async fn delete(&self) -> anyhow::Result<()> {
+        let sql = "DELETE FROM user_opt_outs WHERE user_id = ?1 AND guild_id = ?2";
+
+        {
+            let _guard = WRITE_LOCK.lock().await;
+            let conn = get_write_connection()?;
+            conn.execute(
+                sql,
+                (self.user_id as i64, self.guild_id.unwrap_or(0) as i64),
+            )
+            .await
+            .context("Failed to delete opt-out")?;
+        }
+
+        tracing::debug!("Deleted opt-out: {:?}", self);
+
+        request_push();
+
+        Ok(())
+    }
+
+    /// Whether the user opted out everywhere, or in the guild.
+    // Uses the read pool - no lock needed.
+    pub(super) async fn exists_for(user_id: u64, guild_id: Option<u64>) -> anyhow::Result<bool> {
+        let conn = get_read_connection().await?;
+
+        let sql = r#"
+            SELECT 1 FROM user_opt_outs
+            WHERE user_id = ?1 AND guild_id IN (0, ?2)
+            LIMIT 1
+        "#;
+
+        let mut rows = conn
+            .query(sql, (user_id as i64, guild_id.unwrap_or(0) as i64))
+            .await
+            .context("Failed to execute SELECT query for opt-outs")?;
+
+        Ok(rows.next().await.context("Failed to fetch row")?.is_some())
+    }
+}
+
 /// Represents the Author of the Message used for finding a match.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageAuthor {
@@ -126,6 +492,9 @@ pub struct ResponseMap {
     pub bot_message_id: u64,
     pub guild_id: Option<u64>,
     pub channel_id: u64,
+    /// Whether the bot's message is a webhook repost that replaced the user's
+    /// message (which is deleted).
+    pub reposted: bool,
 }
 
 impl ResponseMap {
@@ -135,14 +504,23 @@ impl ResponseMap {
             bot_message_id: bot_message_id.get(),
             guild_id: user_message.guild_id.map(|id| id.get()),
             channel_id: user_message.channel_id.into(),
+            reposted: false,
+        }
+    }
+
+    /// Maps a user's message to the webhook repost that replaced it.
+    pub fn repost(user_message: &Message, repost_id: Id<MessageMarker>) -> Self {
+        Self {
+            reposted: true,
+            ..Self::new(user_message, repost_id)
         }
     }
 
-    pub async fn save(&self) -> anyhow::Result<()> {
+    pub(super) async fn save(&self) -> anyhow::Result<()> {
         let sql = r#"
             INSERT OR REPLACE INTO response_map
-            (user_message_id, bot_message_id, guild_id, channel_id)
-            VALUES (?1, ?2, ?3, ?4)
+            (user_message_id, bot_message_id, guild_id, channel_id, reposted, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
         "#;
 
         {
@@ -155,6 +533,8 @@ impl ResponseMap {
                     self.bot_message_id as i64,
                     self.guild_id.map(|id| id as i64),
                     self.channel_id as i64,
+                    self.reposted,
+                    unix_now(),
                 ),
             )
             .await
@@ -181,13 +561,13 @@ impl ResponseMap {
     ///     - MessageAuthor (enum): to identify the message author
     // Uses the read pool - no lock needed, safe to run concurrently
     // with writes/push/pull under WAL.
-    pub async fn find_match(
+    pub(super) async fn find_match(
         message_id: Id<MessageMarker>,
     ) -> anyhow::Result<Option<(Self, MessageAuthor)>> {
         let conn = get_read_connection().await?;
 
         let sql = r#"
-            SELECT user_message_id, bot_message_id, guild_id, channel_id
+            SELECT user_message_id, bot_message_id, guild_id, channel_id, reposted
             FROM response_map
             WHERE user_message_id = ?1 OR bot_message_id = ?1
         "#;
@@ -202,11 +582,12 @@ impl ResponseMap {
             return Ok(None);
         };
 
-        let (user_message_id, bot_message_id, guild_id, channel_id) = (
+        let (user_message_id, bot_message_id, guild_id, channel_id, reposted) = (
             row.get::<i64>(0)? as u64,
             row.get::<i64>(1)? as u64,
             row.get::<Option<i64>>(2)?.map(|id| id as u64),
             row.get::<i64>(3)? as u64,
+            row.get::<bool>(4)?,
         );
 
         let author = match user_message_id == message_id.get() {
@@ -221,12 +602,65 @@ impl ResponseMap {
             bot_message_id,
             guild_id,
             channel_id,
+            reposted,
         };
 
         Ok(Some((response_map, author)))
     }
 
-    pub async fn delete_entry(user_message_id: u64) -> anyhow::Result<()> {
+    /// Finds the matches for any of the message ids, as in `find_match`.
+    // Uses the read pool - no lock needed.
+    pub(super) async fn find_matches(
+        message_ids: &[Id<MessageMarker>],
+    ) -> anyhow::Result<Vec<(Self, MessageAuthor)>> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let conn = get_read_connection().await?;
+
+        let placeholders = placeholders(message_ids.len());
+        let sql = format!(
+            r#"
+            SELECT user_message_id, bot_message_id, guild_id, channel_id, reposted
+            FROM response_map
+            WHERE user_message_id IN ({placeholders}) OR bot_message_id IN ({placeholders})
+            "#
+        );
+        let params = message_ids
+            .iter()
+            .map(|id| id.get() as i64)
+            .collect::<Vec<_>>();
+
+        let mut rows = conn
+            .query(sql, params)
+            .await
+            .context("Failed to execute SELECT statement")?;
+
+        let mut matches = Vec::new();
+        while let Some(row) = rows.next().await.context("Failed to fetch row")? {
+            let response_map = Self {
+                user_message_id: row.get::<i64>(0)? as u64,
+                bot_message_id: row.get::<i64>(1)? as u64,
+                guild_id: row.get::<Option<i64>>(2)?.map(|id| id as u64),
+                channel_id: row.get::<i64>(3)? as u64,
+                reposted: row.get::<bool>(4)?,
+            };
+
+            let author = match message_ids.contains(&Id::new(response_map.user_message_id)) {
+                true => MessageAuthor::User,
+                false => MessageAuthor::Bot,
+            };
+
+            matches.push((response_map, author));
+        }
+
+        tracing::debug!("Found {} response maps", matches.len());
+
+        Ok(matches)
+    }
+
+    pub(super) async fn delete_entry(user_message_id: u64) -> anyhow::Result<()> {
         let sql = "DELETE FROM response_map WHERE user_message_id = ?";
 
         {
@@ -246,4 +680,140 @@ impl ResponseMap {
 
         Ok(())
     }
+
+    /// Deletes the entries of every user message in a single statement.
+    pub(super) async fn delete_entries(user_message_ids: &[u64]) -> anyhow::Result<()> {
+        if user_message_ids.is_empty() {
+            return Ok(());
+        }
+
+        let sql = format!(
+            "DELETE FROM response_map WHERE user_message_id IN ({})",
+            placeholders(user_message_ids.len())
+        );
+        let params = user_message_ids
+            .iter()
+            .map(|&id| id as i64)
+            .collect::<Vec<_>>();
+
+        let deleted = {
+            let _guard = WRITE_LOCK.lock().await;
+            let conn = get_write_connection()?;
+            conn.execute(sql, params)
+                .await
+                .context("Failed to delete from response map")?
+        };
+
+        tracing::debug!("Deleted {} response maps", deleted);
+
+        request_push();
+
+        Ok(())
+    }
+
+    /// Deletes every entry in a channel (or thread) that no longer exists.
+    pub(super) async fn delete_channel(guild_id: u64, channel_id: u64) -> anyhow::Result<()> {
+        let sql = "DELETE FROM response_map WHERE guild_id = ?1 AND channel_id = ?2";
+
+        let deleted = {
+            let _guard = WRITE_LOCK.lock().await;
+            let conn = get_write_connection()?;
+            conn.execute(sql, (guild_id as i64, channel_id as i64))
+                .await
+                .context("Failed to delete channel from response map")?
+        };
+
+        tracing::debug!(
+            "Deleted {} response maps for channel_id={}",
+            deleted,
+            channel_id
+        );
+
+        request_push();
+
+        Ok(())
+    }
+
+    /// Deletes every entry in a guild the bot was removed from.
+    pub(super) async fn delete_guild(guild_id: u64) -> anyhow::Result<()> {
+        let sql = "DELETE FROM response_map WHERE guild_id = ?";
+
+        let deleted = {
+            let _guard = WRITE_LOCK.lock().await;
+            let conn = get_write_connection()?;
+            conn.execute(sql, [guild_id as i64])
+                .await
+                .context("Failed to delete guild from response map")?
+        };
+
+        tracing::debug!(
+            "Deleted {} response maps for guild_id={}",
+            deleted,
+            guild_id
+        );
+
+        request_push();
+
+        Ok(())
+    }
+}
+
+/// Numbered placeholders (`?1, ?2, ...`) for an `IN` list, so the same
+/// parameters can be bound to more than one list.
+fn placeholders(len: usize) -> String {
+    (1..=len)
+        .map(|idx| format!("?{idx}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_channel_applies_overrides() {
+        let mut config = ServerConfig::new(1);
+        config.channel_overrides.insert(
+            10,
+            ChannelConfig {
+                sanitizer_mode: Some(SanitizerMode::ManualMention),
+                hide_original_embed: Some(false),
+                ..Default::default()
+            },
+        );
+
+        let channel = config.for_channel(10, None);
+        assert_eq!(channel.sanitizer_mode, SanitizerMode::ManualMention);
+        assert_eq!(channel.delete_permission, config.delete_permission);
+        assert!(!channel.hide_original_embed);
+
+        // A thread in the channel inherits from it.
+        let thread = config.for_channel(11, Some(10));
+        assert_eq!(thread.sanitizer_mode, SanitizerMode::ManualMention);
+
+        assert_eq!(config.for_channel(12, None), config);
+    }
+
+    #[test]
+    fn test_is_sanitized_for_roles() {
+        let (member, bot, muted) = (Id::new(1), Id::new(2), Id::new(3));
+        let mut config = ServerConfig::new(1);
+        assert!(config.is_sanitized_for(&[]));
+
+        config.exempt_roles.insert(muted.get());
+        assert!(config.is_sanitized_for(&[member]));
+        assert!(!config.is_sanitized_for(&[member, muted]));
+
+        config.allowed_roles.insert(member.get());
+        assert!(config.is_sanitized_for(&[member]));
+        assert!(!config.is_sanitized_for(&[bot]));
+        assert!(!config.is_sanitized_for(&[]));
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(placeholders(1), "?1");
+        assert_eq!(placeholders(3), "?1, ?2, ?3");
+    }
 }
@@ -0,0 +1,284 @@
+//! Storage traits the bot is written against, so the database can be swapped out
+//! (e.g. for the in-memory store in tests).
+
+use async_trait::async_trait;
+use twilight_model::id::Id;
+use twilight_model::id::marker::MessageMarker;
+
+use super::operations::{ConfigChange, MessageAuthor, OptOut, ResponseMap, ServerConfig};
+
+/// Where server configs are kept.
+#[async_trait]
+pub trait ConfigStore: Send + Sync {
+    async fn get_config(&self, guild_id: u64) -> anyhow::Result<Option<ServerConfig>>;
+
+    async fn save_config(&self, config: &ServerConfig) -> anyhow::Result<()>;
+
+    /// Returns the latest save of every config saved at or after `since` (by any
+    /// instance sharing the store).
+    async fn changed_since(&self, since: i64) -> anyhow::Result<Vec<ConfigChange>>;
+}
+
+/// Where the links between user messages and the bot's responses are kept.
+#[async_trait]
+pub trait ResponseStore: Send + Sync {
+    async fn save_response(&self, response_map: &ResponseMap) -> anyhow::Result<()>;
+
+    /// Finds the entry the message belongs to, along with which side it's on.
+    async fn find_match(
+        &self,
+        message_id: Id<MessageMarker>,
+    ) -> anyhow::Result<Option<(ResponseMap, MessageAuthor)>>;
+
+    /// Finds the entries any of the messages belong to.
+    async fn find_matches(
+        &self,
+        message_ids: &[Id<MessageMarker>],
+    ) -> anyhow::Result<Vec<(ResponseMap, MessageAuthor)>>;
+
+    async fn delete_response(&self, user_message_id: u64) -> anyhow::Result<()>;
+
+    async fn delete_responses(&self, user_message_ids: &[u64]) -> anyhow::Result<()>;
+
+    /// Deletes every entry in a channel (or thread).
+    async fn delete_channel(&self, guild_id: u64, channel_id: u64) -> anyhow::Result<()>;
+
+    /// Deletes every entry in a guild.
+    async fn delete_guild(&self, guild_id: u64) -> anyhow::Result<()>;
+}
+
+/// Where users' opt-outs of the bot's replies are kept.
+#[async_trait]
+pub trait OptOutStore: Send + Sync {
+    async fn save_opt_out(&self, opt_out: &OptOut) -> anyhow::Result<()>;
+
+    async fn delete_opt_out(&self, opt_out: &OptOut) -> anyhow::Result<()>;
+
+    /// Whether the user opted out everywhere, or in the guild.
+    async fn is_opted_out(&self, user_id: u64, guild_id: Option<u64>) -> anyhow::Result<bool>;
+}
+
+/// The Turso database set up by `db::init`.
+#[derive(Debug, Clone, Copy)]
+pub struct TursoStore;
+
+#[async_trait]
+impl ConfigStore for TursoStore {
+    async fn get_config(&self, guild_id: u64) -> anyhow::Result<Option<ServerConfig>> {
+        ServerConfig::get(guild_id).await
+    }
+
+    async fn save_config(&self, config: &ServerConfig) -> anyhow::Result<()> {
+        config.save().await
+    }
+
+    async fn changed_since(&self, since: i64) -> anyhow::Result<Vec<ConfigChange>> {
+        ServerConfig::changed_since(since).await
+    }
+}
+
+#[async_trait]
+impl ResponseStore for TursoStore {
+    async fn save_response(&self, response_map: &ResponseMap) -> anyhow::Result<()> {
+        response_map.save().await
+    }
+
+    async fn find_match(
+        &self,
+        message_id: Id<MessageMarker>,
+    ) -> anyhow::Result<Option<(ResponseMap, MessageAuthor)>> {
+        ResponseMap::find_match(message_id).await
+    }
+
+    async fn find_matches(
+        &self,
+        message_ids: &[Id<MessageMarker>],
+    ) -> anyhow::Result<Vec<(ResponseMap, MessageAuthor)>> {
+        ResponseMap::find_matches(message_ids).await
+    }
+
+    async fn delete_response(&self, user_message_id: u64) -> anyhow::Result<()> {
+        ResponseMap::delete_entry(user_message_id).await
+    }
+
+    async fn delete_responses(&self, user_message_ids: &[u64]) -> anyhow::Result<()> {
+        ResponseMap::delete_entries(user_message_ids).await
+    }
+
+    async fn delete_channel(&self, guild_id: u64, channel_id: u64) -> anyhow::Result<()> {
+        ResponseMap::delete_channel(guild_id, channel_id).await
+    }
+
+    async fn delete_guild(&self, guild_id: u64) -> anyhow::Result<()> {
+        ResponseMap::delete_guild(guild_id).await
+    }
+}
+
+#[async_trait]
+impl OptOutStore for TursoStore {
+    async fn save_opt_out(&self, opt_out: &OptOut) -> anyhow::Result<()> {
+        opt_out.save().await
+    }
+
+    async fn delete_opt_out(&self, opt_out: &OptOut) -> anyhow::Result<()> {
+        opt_out.delete().await
+    }
+
+    async fn is_opted_out(&self, user_id: u64, guild_id: Option<u64>) -> anyhow::Result<bool> {
+        OptOut::exists_for(user_id, guild_id).await
+    }
+}
+
+#[cfg(test)]
+pub use memory::MemoryStore;
+
+#[cfg(test)]
+mod memory {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+
+    use super::super::connection::unix_now;
+    use super::*;
+
+    /// Keeps everything in memory, for tests.
+    #[derive(Debug, Default)]
+    pub struct MemoryStore {
+        /// Configs along with their latest save.
+        configs: Mutex<HashMap<u64, (ServerConfig, ConfigChange)>>,
+        responses: Mutex<HashMap<u64, ResponseMap>>,
+        opt_outs: Mutex<HashSet<OptOut>>,
+    }
+
+    impl MemoryStore {
+        /// Every stored response, in no particular order.
+        pub fn responses(&self) -> Vec<ResponseMap> {
+            self.responses.lock().unwrap().values().copied().collect()
+        }
+
+        /// Saves a config as if it was saved at `saved_at`, e.g. by an instance
+        /// with a skewed clock or pushed late.
+        pub fn save_config_at(&self, config: &ServerConfig, saved_at: i64) {
+            let mut configs = self.configs.lock().unwrap();
+            let version = configs
+                .get(&config.guild_id)
+                .map_or(1, |(_, change)| change.version + 1);
+            let change = ConfigChange {
+                guild_id: config.guild_id,
+                updated_at: saved_at,
+                version,
+            };
+            configs.insert(config.guild_id, (config.clone(), change));
+        }
+    }
+
+    #[async_trait]
+    impl ConfigStore for MemoryStore {
+        async fn get_config(&self, guild_id: u64) -> anyhow::Result<Option<ServerConfig>> {
+            let configs = self.configs.lock().unwrap();
+            Ok(configs.get(&guild_id).map(|(config, _)| config.clone()))
+        }
+
+        async fn save_config(&self, config: &ServerConfig) -> anyhow::Result<()> {
+            self.save_config_at(config, unix_now());
+            Ok(())
+        }
+
+        async fn changed_since(&self, since: i64) -> anyhow::Result<Vec<ConfigChange>> {
+            let configs = self.configs.lock().unwrap();
+            Ok(configs
+                .values()
+                .filter(|(_, change)| change.updated_at >= since)
+                .map(|&(_, change)| change)
+                .collect())
+        }
+    }
+
+    #[async_trait]
+    impl ResponseStore for MemoryStore {
+        async fn save_response(&self, response_map: &ResponseMap) -> anyhow::Result<()> {
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(response_map.user_message_id, *response_map);
+            Ok(())
+        }
+
+        async fn find_match(
+            &self,
+            message_id: Id<MessageMarker>,
+        ) -> anyhow::Result<Option<(ResponseMap, MessageAuthor)>> {
+            Ok(self.find_matches(&[message_id]).await?.pop())
+        }
+
+        async fn find_matches(
+            &self,
+            message_ids: &[Id<MessageMarker>],
+        ) -> anyhow::Result<Vec<(ResponseMap, MessageAuthor)>> {
+            let ids = message_ids.iter().map(|id| id.get()).collect::<Vec<_>>();
+
+            Ok(self
+                .responses
+                .lock()
+                .unwrap()
+                .values()
+                .filter_map(|response_map| {
+                    if ids.contains(&response_map.user_message_id) {
+                        Some((*response_map, MessageAuthor::User))
+                    } else if ids.contains(&response_map.bot_message_id) {
+                        Some((*response_map, MessageAuthor::Bot))
+                    } else {
+                        None
+                    }
+                })
+                .collect())
+        }
+
+        async fn delete_response(&self, user_message_id: u64) -> anyhow::Result<()> {
+            self.responses.lock().unwrap().remove(&user_message_id);
+            Ok(())
+        }
+
+        async fn delete_responses(&self, user_message_ids: &[u64]) -> anyhow::Result<()> {
+            let mut responses = self.responses.lock().unwrap();
+            for id in user_message_ids {
+                responses.remove(id);
+            }
+            Ok(())
+        }
+
+        async fn delete_channel(&self, guild_id: u64, channel_id: u64) -> anyhow::Result<()> {
+            self.responses.lock().unwrap().retain(|_, response_map| {
+                response_map.guild_id != Some(guild_id) || response_map.channel_id != channel_id
+            });
+            Ok(())
+        }
+
+        async fn delete_guild(&self, guild_id: u64) -> anyhow::Result<()> {
+            self.responses
+                .lock()
+                .unwrap()
+                .retain(|_, response_map| response_map.guild_id != Some(guild_id));
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl OptOutStore for MemoryStore {
+        async fn save_opt_out(&self, opt_out: &OptOut) -> anyhow::Result<()> {
+            self.opt_outs.lock().unwrap().insert(*opt_out);
+            Ok(())
+        }
+
+        async fn delete_opt_out(&self, opt_out: &OptOut) -> anyhow::Result<()> {
+            self.opt_outs.lock().unwrap().remove(opt_out);
+            Ok(())
+        }
+
+        async fn is_opted_out(&self, user_id: u64, guild_id: Option<u64>) -> anyhow::Result<bool> {
+            let opt_outs = self.opt_outs.lock().unwrap();
+            Ok([None, guild_id]
+                .into_iter()
+                .any(|guild_id| opt_outs.contains(&OptOut { user_id, guild_id })))
+        }
+    }
+}
@@ -33,6 +33,9 @@ impl HelpCommand {
         -  **TikTok & Instagram**: Thanks to [kkScript](https://kkscript.com/)\n\
         -  **Instagram** (Fallback): Powered by the awesome [InstaFix](https://github.com/Wikidepia/InstaFix) project\n\
         -  **Reddit**: Thanks to the [vxReddit](https://github.com/dylanpdx/vxReddit) project\n\
+        -  **Bluesky**: Thanks to the [FxEmbed](https://github.com/FxEmbed/FxEmbed) project\n\
+        -  **Twitch**: Thanks to the [FxTwitch](https://github.com/seriaati/fxtwitch) project\n\
+        -# Rather I didn't fix your links? Use `/optout`, or add `—x` to a message to skip just that one.\n\
         -# The code that powers me is publicly sourced [here](https://github.com/suhaybu/sanitizer-bot) on GitHub along with the [Privacy policy](https://github.com/suhaybu/sanitizer-bot/blob/master/PRIVACY.md).\n\
             ")
             .build();
@@ -1,12 +1,14 @@
 //! Stores business logic for commands
 
 mod help;
+mod optout;
 mod sanitize;
 mod settings;
 
 use std::sync::Arc;
 
 pub use help::HelpCommand;
+pub use optout::OptOutCommand;
 pub use sanitize::SanitizeCommand;
 pub use settings::SettingsCommand;
 use twilight_http::Client;
@@ -18,6 +20,8 @@ pub async fn register_global_commands(client: &Arc<Client>) -> anyhow::Result<()
         settings::SettingsCommand::create_command(),
         sanitize::SanitizeCommand::create_command(),
         sanitize::SanitizeCommand::create_command_message(),
+        optout::OptOutCommand::create_command(),
+        optout::OptOutCommand::create_command_optin(),
     ];
     let application = client.current_user_application().await?.model().await?;
 
@@ -0,0 +1,135 @@
+//! Opt-out Commands: Lets users stop (and restart) the bot replying to their messages.
+
+use anyhow::Context;
+use twilight_http::Client;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::{
+    CommandData, CommandOptionValue,
+};
+use twilight_model::application::interaction::{Interaction, InteractionContextType};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType};
+use twilight_model::oauth::ApplicationIntegrationType;
+use twilight_util::builder::InteractionResponseDataBuilder;
+use twilight_util::builder::command::{BooleanBuilder, CommandBuilder};
+
+use crate::db::{OptOut, OptOutStore};
+
+pub struct OptOutCommand;
+
+impl OptOutCommand {
+    /// Creates /optout command.
+    pub fn create_command() -> Command {
+        CommandBuilder::new(
+            "optout",
+            "Stop me from fixing the links in your messages 🔕",
+            CommandType::ChatInput,
+        )
+        .contexts([InteractionContextType::Guild, InteractionContextType::BotDm])
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .option(BooleanBuilder::new("server", "Only opt out in this server?").required(false))
+        .build()
+    }
+
+    /// Creates /optin command.
+    pub fn create_command_optin() -> Command {
+        CommandBuilder::new(
+            "optin",
+            "Let me fix the links in your messages again 🔔",
+            CommandType::ChatInput,
+        )
+        .contexts([InteractionContextType::Guild, InteractionContextType::BotDm])
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .build()
+    }
+
+    /// Handles responding to /optout invocation.
+    pub async fn handle(
+        ctx: &Interaction,
+        client: &Client,
+        data: &CommandData,
+        opt_outs: &dyn OptOutStore,
+    ) -> anyhow::Result<()> {
+        let user_id = ctx
+            .author_id()
+            .context("User ID could not be found in interaction.")?;
+
+        let only_server = data
+            .options
+            .iter()
+            .find_map(|o| match o.value {
+                CommandOptionValue::Boolean(b) if o.name == "server" => Some(b),
+                _ => None,
+            })
+            .unwrap_or(false);
+
+        // There's no server to scope the opt-out to in DMs.
+        let guild_id = ctx.guild_id.filter(|_| only_server).map(|id| id.get());
+        opt_outs
+            .save_opt_out(&OptOut {
+                user_id: user_id.get(),
+                guild_id,
+            })
+            .await?;
+
+        let scope = match guild_id {
+            Some(_) => " in this server",
+            None => "",
+        };
+        let content = format!(
+            "🔕 I won't fix the links in your messages{} anymore. Use `/optin` to undo this.\n\
+            -# To skip a single message instead, add `—x` anywhere in it.",
+            scope
+        );
+
+        Self::respond_ephemeral(ctx, client, content).await
+    }
+
+    /// Handles responding to /optin invocation, undoing both the global opt-out
+    /// and the one for the current server.
+    pub async fn handle_optin(
+        ctx: &Interaction,
+        client: &Client,
+        opt_outs: &dyn OptOutStore,
+    ) -> anyhow::Result<()> {
+        let user_id = ctx
+            .author_id()
+            .context("User ID could not be found in interaction.")?;
+
+        for guild_id in [None, ctx.guild_id.map(|id| id.get())] {
+            opt_outs
+                .delete_opt_out(&OptOut {
+                    user_id: user_id.get(),
+                    guild_id,
+                })
+                .await?;
+        }
+
+        let content = "🔔 I'll fix the links in your messages again.".to_string();
+        Self::respond_ephemeral(ctx, client, content).await
+    }
+
+    /// Responds to the interaction with an ephemeral text message.
+    async fn respond_ephemeral(
+        ctx: &Interaction,
+        client: &Client,
+        content: String,
+    ) -> anyhow::Result<()> {
+        let response_data = InteractionResponseDataBuilder::new()
+            .content(content)
+            .flags(MessageFlags::EPHEMERAL)
+            .build();
+
+        let response = InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(response_data),
+        };
+
+        client
+            .interaction(ctx.application_id)
+            .create_response(ctx.id, &ctx.token, &response)
+            .await?;
+
+        Ok(())
+    }
+}
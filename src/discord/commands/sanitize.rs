@@ -14,6 +14,8 @@ use twilight_model::oauth::ApplicationIntegrationType;
 use twilight_util::builder::command::{BooleanBuilder, CommandBuilder, StringBuilder};
 
 use crate::sanitize::UrlProcessor;
+use crate::utils::cache::ConfigCache;
+use crate::utils::markdown;
 
 pub struct SanitizeCommand;
 
@@ -69,6 +71,7 @@ impl SanitizeCommand {
         ctx: &Interaction,
         client: &Client,
         data: &CommandData,
+        configs: &ConfigCache,
     ) -> anyhow::Result<()> {
         // Acknowledge the user interaction first.
         let deferred_response = InteractionResponse {
@@ -123,7 +126,12 @@ impl SanitizeCommand {
             _ => anyhow::bail!("Unexpected CommandType: {:?}", data.kind),
         };
 
-        let url = match UrlProcessor::try_new(user_input, is_spoiler) {
+        // Uses the first supported link, spoilered if requested or if it's inside `||`.
+        let url = markdown::extract_links(user_input)
+            .into_iter()
+            .find_map(|link| UrlProcessor::try_new(link.url, is_spoiler || link.spoiler));
+
+        let url = match url {
             Some(url) => url,
             None => {
                 client
@@ -137,16 +145,28 @@ impl SanitizeCommand {
             }
         };
 
-        let original_url = url
-            .get_original_url()
-            .expect("Original URL could not be retrieved.");
+        // Applies the guild's preferences when invoked inside a guild.
+        let server_config = match ctx.guild_id {
+            Some(guild_id) => match configs.get_or_fetch(guild_id.get()).await {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    tracing::warn!("Failed to fetch server config, using defaults: {:?}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let url = url.with_config(server_config.as_ref());
+
+        // The original url is retrieved after capturing, since share links resolve
+        // to a canonical url.
+        let processed = url.capture_url().await.and_then(|captures| {
+            let original_url = captures.get_original_url()?;
+            Some((original_url, captures.format_output()?))
+        });
 
-        let output = match url
-            .capture_url()
-            .await
-            .and_then(|captures| captures.format_output())
-        {
-            Some(output) => output,
+        let (original_url, output) = match processed {
+            Some(processed) => processed,
             None => {
                 client
                     .interaction(ctx.application_id)
@@ -1,29 +1,51 @@
 //! Settings Command: Creates a Settings Container allowing users to configure the bot's behavior.
 
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
 use anyhow::Context;
 use twilight_http::Client;
 use twilight_model::application::command::{Command, CommandType};
+use twilight_model::application::interaction::application_command::{
+    CommandData, CommandOptionValue,
+};
 use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::application::interaction::modal::{
+    ModalInteractionComponent, ModalInteractionData,
+};
 use twilight_model::application::interaction::{Interaction, InteractionContextType};
+use twilight_model::channel::ChannelType;
 use twilight_model::channel::message::component::{
-    Component, Container, SelectMenuType, SeparatorSpacingSize,
+    ButtonStyle, Component, Container, Section, SelectDefaultValue, SelectMenu, SelectMenuOption,
+    SelectMenuType, SeparatorSpacingSize, TextInput, TextInputStyle,
 };
 use twilight_model::channel::message::{EmojiReactionType, MessageFlags};
 use twilight_model::guild::Permissions;
-use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType};
+use twilight_model::http::interaction::{
+    InteractionResponse, InteractionResponseData, InteractionResponseType,
+};
+use twilight_model::id::Id;
 use twilight_model::oauth::ApplicationIntegrationType;
 use twilight_util::builder::InteractionResponseDataBuilder;
-use twilight_util::builder::command::CommandBuilder;
+use twilight_util::builder::command::{ChannelBuilder, CommandBuilder};
 use twilight_util::builder::message::{
-    ActionRowBuilder, ContainerBuilder, SelectMenuBuilder, SelectMenuOptionBuilder,
-    SeparatorBuilder, TextDisplayBuilder,
+    ActionRowBuilder, ButtonBuilder, ContainerBuilder, LabelBuilder, SectionBuilder,
+    SelectMenuBuilder, SelectMenuOptionBuilder, SeparatorBuilder, TextDisplayBuilder,
 };
 
 use crate::db::ServerConfig;
 use crate::discord::models::{
     DeletePermission, HideOriginalEmbed, SanitizerMode, SettingsMenuType,
 };
-use crate::utils::cache;
+use crate::sanitize::platforms;
+use crate::utils::cache::ConfigCache;
+
+/// Discord's limit on the number of options in a select menu.
+const MAX_SELECT_OPTIONS: usize = 25;
+/// Value of the option that makes a channel follow the server's setting.
+const INHERIT: &str = "inherit";
+/// Custom id of the Reply Format button, modal and its text input.
+const REPLY_TEMPLATE: &str = "reply_template";
 
 pub struct SettingsCommand;
 
@@ -38,11 +60,31 @@ impl SettingsCommand {
         .default_member_permissions(Permissions::MANAGE_GUILD)
         .contexts([InteractionContextType::Guild])
         .integration_types([ApplicationIntegrationType::GuildInstall])
+        .option(
+            ChannelBuilder::new(
+                "channel",
+                "Override the settings in a single channel (and its threads)",
+            )
+            .channel_types([
+                ChannelType::GuildText,
+                ChannelType::GuildAnnouncement,
+                ChannelType::GuildForum,
+                ChannelType::GuildMedia,
+                ChannelType::GuildVoice,
+                ChannelType::GuildStageVoice,
+            ])
+            .required(false),
+        )
         .build()
     }
 
     /// Handles responding to command invocation.
-    pub async fn handle(ctx: &Interaction, client: &Client) -> anyhow::Result<()> {
+    pub async fn handle(
+        ctx: &Interaction,
+        client: &Client,
+        data: &CommandData,
+        configs: &ConfigCache,
+    ) -> anyhow::Result<()> {
         let Some(guild_id) = ctx.guild_id else {
             anyhow::bail!("Settings can only be used in guilds!")
         };
@@ -74,9 +116,17 @@ impl SettingsCommand {
         }
 
         // Get current server configuration
-        let config = cache::load().get_or_fetch(guild_id.get()).await?;
+        let config = configs.get_or_fetch(guild_id.get()).await?;
 
-        let settings_container = Self::construct_container(&config);
+        let channel_id = data.options.iter().find_map(|o| match o.value {
+            CommandOptionValue::Channel(id) if o.name == "channel" => Some(id.get()),
+            _ => None,
+        });
+
+        let settings_container = match channel_id {
+            Some(channel_id) => Self::construct_channel_container(&config, channel_id),
+            None => Self::construct_container(&config),
+        };
         let data = InteractionResponseDataBuilder::new()
             .components([Component::Container(settings_container)])
             .flags(MessageFlags::IS_COMPONENTS_V2 | MessageFlags::EPHEMERAL)
@@ -95,28 +145,59 @@ impl SettingsCommand {
         Ok(())
     }
 
-    /// Handles responding to component (Container) invocations
+    /// Handles responding to component (Container) invocations, for the
+    /// overrides of `channel_id` if set.
     pub async fn handle_component(
         ctx: &Interaction,
         menu_type: SettingsMenuType,
+        channel_id: Option<u64>,
         data: &MessageComponentInteractionData,
         client: &Client,
+        configs: &ConfigCache,
     ) -> anyhow::Result<()> {
         let Some(guild_id) = ctx.guild_id else {
             anyhow::bail!("Settings can only be used in guilds!")
         };
 
-        let selected_value = data
-            .values
-            .first()
-            .context("No value selected from dropdown")?;
+        // Multi-select menus may be submitted with nothing selected.
+        let selected_value = match menu_type.is_multi_select() {
+            true => "",
+            false => data
+                .values
+                .first()
+                .context("No value selected from dropdown")?,
+        };
 
         // Debug logging to see what value we're trying to parse
         tracing::debug!("Selected value from dropdown: '{}'", selected_value);
         tracing::debug!("Menu type: {:?}", menu_type);
 
-        let cache = cache::load();
-        let mut config = cache.get_or_fetch(guild_id.get()).await?;
+        let mut config = configs.get_or_fetch(guild_id.get()).await?;
+
+        if let Some(channel_id) = channel_id {
+            let overrides = config.channel_overrides.entry(channel_id).or_default();
+            match menu_type {
+                SettingsMenuType::SanitizerMode => {
+                    overrides.sanitizer_mode = Self::parse_override(selected_value)?
+                }
+                SettingsMenuType::DeletePermission => {
+                    overrides.delete_permission = Self::parse_override(selected_value)?
+                }
+                SettingsMenuType::HideOriginalEmbed => {
+                    overrides.hide_original_embed = Self::parse_override(selected_value)?
+                        .map(|hide_setting| matches!(hide_setting, HideOriginalEmbed::On))
+                }
+                _ => anyhow::bail!("{:?} can't be overridden per channel", menu_type),
+            }
+            if overrides.is_empty() {
+                config.channel_overrides.remove(&channel_id);
+            }
+
+            configs.update_config(guild_id.get(), config).await?;
+
+            let confirmation_msg = format!("✅ Settings for <#{}> updated", channel_id);
+            return Self::respond_ephemeral(ctx, client, confirmation_msg).await;
+        }
 
         // Update the appropriate setting
         match menu_type {
@@ -136,18 +217,139 @@ impl SettingsCommand {
                     .with_context(|| format!("Invalid hide embed setting: '{}'", selected_value))?;
                 config.hide_original_embed = matches!(hide_setting, HideOriginalEmbed::On);
             }
+            SettingsMenuType::ReplacementDomains => {
+                match Self::parse_replacement_domains(&data.values) {
+                    Ok(domains) => config.replacement_domains = domains,
+                    Err(e) => {
+                        return Self::respond_ephemeral(ctx, client, format!("❌ {}", e)).await;
+                    }
+                }
+            }
+            SettingsMenuType::EnabledPlatforms => {
+                config.disabled_platforms = Self::parse_enabled_platforms(&data.values)?
+            }
+            SettingsMenuType::AllowedRoles => {
+                config.allowed_roles = Self::parse_roles(&data.values)?
+            }
+            SettingsMenuType::ExemptRoles => config.exempt_roles = Self::parse_roles(&data.values)?,
+            SettingsMenuType::ModeratorRoles => {
+                config.moderator_roles = Self::parse_roles(&data.values)?
+            }
         }
 
-        cache.update_config(guild_id.get(), config).await?;
+        configs.update_config(guild_id.get(), config).await?;
 
         let confirmation_msg = match menu_type {
             SettingsMenuType::SanitizerMode => "✅ Sanitizer Mode updated".to_string(),
             SettingsMenuType::DeletePermission => "✅ Delete Permission updated".to_string(),
             SettingsMenuType::HideOriginalEmbed => "✅ Original Link Preview updated".to_string(),
+            SettingsMenuType::ReplacementDomains => "✅ Embed Providers updated".to_string(),
+            SettingsMenuType::EnabledPlatforms => "✅ Platforms updated".to_string(),
+            SettingsMenuType::AllowedRoles | SettingsMenuType::ExemptRoles => {
+                "✅ Sanitized Roles updated".to_string()
+            }
+            SettingsMenuType::ModeratorRoles => "✅ Moderator Roles updated".to_string(),
+        };
+
+        Self::respond_ephemeral(ctx, client, confirmation_msg).await
+    }
+
+    /// Handles the Reply Format button, opening the modal to edit it.
+    pub async fn handle_reply_template_button(
+        ctx: &Interaction,
+        client: &Client,
+        configs: &ConfigCache,
+    ) -> anyhow::Result<()> {
+        let Some(guild_id) = ctx.guild_id else {
+            anyhow::bail!("Settings can only be used in guilds!")
+        };
+
+        let config = configs.get_or_fetch(guild_id.get()).await?;
+        let response = InteractionResponse {
+            kind: InteractionResponseType::Modal,
+            data: Some(Self::construct_reply_template_modal(&config)),
         };
 
+        client
+            .interaction(ctx.application_id)
+            .create_response(ctx.id, &ctx.token, &response)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Handles the submitted Reply Format modal, updating the settings in place
+    /// so the preview shows the new format.
+    pub async fn handle_reply_template_modal(
+        ctx: &Interaction,
+        client: &Client,
+        data: &ModalInteractionData,
+        configs: &ConfigCache,
+    ) -> anyhow::Result<()> {
+        let Some(guild_id) = ctx.guild_id else {
+            anyhow::bail!("Settings can only be used in guilds!")
+        };
+
+        let template = Self::find_text_input(&data.components, REPLY_TEMPLATE)
+            .context("Reply format missing from modal")?
+            .trim();
+        // An empty format goes back to the default.
+        if !template.is_empty()
+            && let Err(e) = platforms::validate_label(template)
+        {
+            return Self::respond_ephemeral(ctx, client, format!("❌ {}", e)).await;
+        }
+
+        let mut config = configs.get_or_fetch(guild_id.get()).await?;
+        config.reply_template = Some(template.to_string()).filter(|t| !t.is_empty());
+        let settings_container = Self::construct_container(&config);
+        configs.update_config(guild_id.get(), config).await?;
+
+        let response = InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(
+                InteractionResponseDataBuilder::new()
+                    .components([Component::Container(settings_container)])
+                    .flags(MessageFlags::IS_COMPONENTS_V2)
+                    .build(),
+            ),
+        };
+
+        client
+            .interaction(ctx.application_id)
+            .create_response(ctx.id, &ctx.token, &response)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Finds the value of a text input in a submitted modal.
+    fn find_text_input<'a>(
+        components: &'a [ModalInteractionComponent],
+        custom_id: &str,
+    ) -> Option<&'a str> {
+        components.iter().find_map(|component| match component {
+            ModalInteractionComponent::Label(label) => {
+                Self::find_text_input(std::slice::from_ref(label.component.as_ref()), custom_id)
+            }
+            ModalInteractionComponent::ActionRow(row) => {
+                Self::find_text_input(&row.components, custom_id)
+            }
+            ModalInteractionComponent::TextInput(input) if input.custom_id == custom_id => {
+                Some(input.value.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Responds to the interaction with an ephemeral text message.
+    async fn respond_ephemeral(
+        ctx: &Interaction,
+        client: &Client,
+        content: String,
+    ) -> anyhow::Result<()> {
         let response_data = InteractionResponseDataBuilder::new()
-            .content(confirmation_msg)
+            .content(content)
             .flags(MessageFlags::EPHEMERAL)
             .build();
 
@@ -164,9 +366,197 @@ impl SettingsCommand {
         Ok(())
     }
 
+    /// Parses the value picked in a channel's menu, `None` meaning the server's
+    /// setting applies.
+    fn parse_override<T>(value: &str) -> anyhow::Result<Option<T>>
+    where
+        T: FromStr<Err = anyhow::Error>,
+    {
+        match value {
+            INHERIT => Ok(None),
+            value => value.parse().map(Some),
+        }
+    }
+
+    /// Parses the platform ids picked in the Platforms menu, returning the ones
+    /// left out.
+    fn parse_enabled_platforms(values: &[String]) -> anyhow::Result<HashSet<String>> {
+        let registry = platforms::load();
+        if let Some(unknown) = values.iter().find(|id| registry.get(id).is_none()) {
+            anyhow::bail!("Unknown platform: '{}'", unknown);
+        }
+
+        Ok(registry
+            .iter()
+            .map(|platform| platform.id())
+            .filter(|id| !values.iter().any(|value| value == id))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Parses the role ids picked in a role menu.
+    fn parse_roles(values: &[String]) -> anyhow::Result<HashSet<u64>> {
+        values
+            .iter()
+            .map(|value| {
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid role: '{}'", value))
+            })
+            .collect()
+    }
+
+    /// Parses the `platform:domain` values of the Embed Providers menu, allowing
+    /// one domain per platform. Picking a platform's default clears its override.
+    fn parse_replacement_domains(values: &[String]) -> anyhow::Result<HashMap<String, String>> {
+        let registry = platforms::load();
+        let mut seen = HashSet::new();
+        let mut domains = HashMap::new();
+
+        for value in values {
+            let (platform_id, domain) = value
+                .split_once(':')
+                .with_context(|| format!("Invalid embed provider: '{}'", value))?;
+            let platform = registry
+                .get(platform_id)
+                .with_context(|| format!("Unknown platform: '{}'", platform_id))?;
+
+            if !platform.is_vetted_domain(domain) {
+                anyhow::bail!(
+                    "{} is not a supported provider for {}",
+                    domain,
+                    platform.display_name()
+                );
+            }
+            if !seen.insert(platform_id) {
+                anyhow::bail!("Pick only one provider for {}", platform.display_name());
+            }
+            if domain != platform.default_domain() {
+                domains.insert(platform_id.to_string(), domain.to_string());
+            }
+        }
+
+        Ok(domains)
+    }
+
+    /// Returns the Platforms menu, with the platforms the guild has enabled picked.
+    fn construct_platform_menu(config: &ServerConfig) -> SelectMenu {
+        let registry = platforms::load();
+        let mut platforms = registry.iter().collect::<Vec<_>>();
+        if platforms.len() > MAX_SELECT_OPTIONS {
+            tracing::warn!(
+                "Too many platforms ({}), only showing the first {}",
+                platforms.len(),
+                MAX_SELECT_OPTIONS
+            );
+            platforms.truncate(MAX_SELECT_OPTIONS);
+        }
+
+        let max_values = platforms.len() as u8;
+        platforms
+            .into_iter()
+            .fold(
+                SelectMenuBuilder::new(
+                    SettingsMenuType::EnabledPlatforms.as_ref(),
+                    SelectMenuType::Text,
+                )
+                .max_values(max_values)
+                .min_values(0)
+                .placeholder("Select Platforms"),
+                |menu, platform| {
+                    let mut option =
+                        SelectMenuOptionBuilder::new(platform.display_name(), platform.id())
+                            .default(config.is_platform_enabled(platform.id()));
+                    if let Some(description) = platform.description() {
+                        option = option.description(description);
+                    }
+                    menu.option(option.build())
+                },
+            )
+            .build()
+    }
+
+    /// Returns a menu picking any number of roles, with `roles` picked.
+    fn construct_role_menu(
+        menu_type: SettingsMenuType,
+        placeholder: &str,
+        roles: &HashSet<u64>,
+    ) -> SelectMenu {
+        let mut roles = roles.iter().copied().collect::<Vec<_>>();
+        roles.sort_unstable();
+
+        SelectMenuBuilder::new(menu_type.as_ref(), SelectMenuType::Role)
+            .max_values(MAX_SELECT_OPTIONS as u8)
+            .min_values(0)
+            .placeholder(placeholder)
+            .default_values(
+                roles
+                    .into_iter()
+                    .map(|id| SelectDefaultValue::Role(Id::new(id)))
+                    .collect(),
+            )
+            .build()
+    }
+
+    /// Returns the Embed Providers menu, listing every vetted domain per platform.
+    fn construct_domain_menu(config: &ServerConfig) -> Option<SelectMenu> {
+        let mut options: Vec<SelectMenuOption> = Vec::new();
+
+        for platform in platforms::load()
+            .iter()
+            .filter(|p| !p.alternative_domains().is_empty())
+        {
+            let current = platform.replacement_domain(
+                config
+                    .replacement_domains
+                    .get(platform.id())
+                    .map(String::as_str),
+            );
+
+            for domain in platform.domains() {
+                let option = SelectMenuOptionBuilder::new(
+                    format!("{}: {}", platform.display_name(), domain),
+                    format!("{}:{}", platform.id(), domain),
+                )
+                .default(domain == current);
+
+                options.push(match domain == platform.default_domain() {
+                    true => option.description("Default").build(),
+                    false => option.build(),
+                });
+            }
+        }
+
+        if options.is_empty() {
+            return None;
+        }
+        if options.len() > MAX_SELECT_OPTIONS {
+            tracing::warn!(
+                "Too many embed providers ({}), only showing the first {}",
+                options.len(),
+                MAX_SELECT_OPTIONS
+            );
+            options.truncate(MAX_SELECT_OPTIONS);
+        }
+
+        let max_values = options.len() as u8;
+        let menu = options.into_iter().fold(
+            SelectMenuBuilder::new(
+                SettingsMenuType::ReplacementDomains.as_ref(),
+                SelectMenuType::Text,
+            )
+            .max_values(max_values)
+            .min_values(0)
+            .placeholder("Select Embed Providers"),
+            |menu, option| menu.option(option),
+        );
+
+        Some(menu.build())
+    }
+
     /// Returns Container embed to be displayed to the user (ComponentsV2).
     fn construct_container(config: &ServerConfig) -> Container {
-        ContainerBuilder::new()
+        let container = ContainerBuilder::new()
             .spoiler(false)
             .component(TextDisplayBuilder::new("## Sanitizer Settings 🛠️").build())
             .component(
@@ -175,65 +565,19 @@ impl SettingsCommand {
                     .spacing(SeparatorSpacingSize::Small)
                     .build(),
             )
-            .component(TextDisplayBuilder::new("### Sanitizer Mode").build())
-            .component(TextDisplayBuilder::new("Change how the bot can be activated.").build())
+            .component(Self::heading("Sanitizer Mode", "Change how the bot can be activated."))
             .component(
                 ActionRowBuilder::new()
                     .component(
-                        SelectMenuBuilder::new(
-                            SettingsMenuType::SanitizerMode.as_ref(),
-                            SelectMenuType::Text,
-                        )
-                        .max_values(1)
-                        .min_values(1)
-                        .placeholder("Select Sanitizer Mode")
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Automatic",
-                                SanitizerMode::Automatic.as_ref(),
-                            )
-                            .default(config.sanitizer_mode == SanitizerMode::Automatic)
-                            .description("Fix links automatically. (Default)")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "🤖".to_string(),
-                            })
-                            .build(),
-                        )
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Manual: Emote",
-                                SanitizerMode::ManualEmote.as_ref(),
-                            )
-                            .default(config.sanitizer_mode == SanitizerMode::ManualEmote)
-                            .description("Fix links once a emoji reaction is added.")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "🎭".to_string(),
-                            })
-                            .build(),
-                        )
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Manual: Mention",
-                                SanitizerMode::ManualMention.as_ref(),
-                            )
-                            .default(config.sanitizer_mode == SanitizerMode::ManualMention)
-                            .description("Fix links in messages mentioning the bot.")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "💬".to_string(),
-                            })
-                            .build(),
-                        )
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Manual: Mention + Emote",
-                                SanitizerMode::ManualBoth.as_ref(),
+                        Self::sanitizer_mode_options(
+                            SelectMenuBuilder::new(
+                                SettingsMenuType::SanitizerMode.as_ref(),
+                                SelectMenuType::Text,
                             )
-                            .default(config.sanitizer_mode == SanitizerMode::ManualBoth)
-                            .description("Fix links either if mentioned or on an emoji reaction.")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "🔁".to_string(),
-                            })
-                            .build(),
+                            .max_values(1)
+                            .min_values(1)
+                            .placeholder("Select Sanitizer Mode"),
+                            Some(config.sanitizer_mode),
                         )
                         .build(),
                     )
@@ -245,58 +589,43 @@ impl SettingsCommand {
                     .spacing(SeparatorSpacingSize::Small)
                     .build(),
             )
-            .component(TextDisplayBuilder::new("### Delete Button").build())
-            .component(
-                TextDisplayBuilder::new(
-                    "Change who is allowed to delete the responses of the bot.",
-                )
-                .build(),
-            )
+            .component(Self::heading("Delete Button", "Change who is allowed to delete the responses of the bot."))
             .component(
                 ActionRowBuilder::new()
                     .component(
-                        SelectMenuBuilder::new(
-                            SettingsMenuType::DeletePermission.as_ref(),
-                            SelectMenuType::Text,
-                        )
-                        .max_values(1)
-                        .min_values(1)
-                        .placeholder("Select Delete Button Permission")
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Author and Mods",
-                                DeletePermission::AuthorAndMods.as_ref(),
+                        Self::delete_permission_options(
+                            SelectMenuBuilder::new(
+                                SettingsMenuType::DeletePermission.as_ref(),
+                                SelectMenuType::Text,
                             )
-                            .default(config.delete_permission == DeletePermission::AuthorAndMods)
-                            .description("Author or users that can Manage Messages. (Default)")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "👥".to_string(),
-                            })
-                            .build(),
+                            .max_values(1)
+                            .min_values(1)
+                            .placeholder("Select Delete Button Permission"),
+                            Some(config.delete_permission),
                         )
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Everyone",
-                                DeletePermission::Everyone.as_ref(),
-                            )
-                            .default(config.delete_permission == DeletePermission::Everyone)
-                            .description("All users, regardless of their permissions.")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "🌐".to_string(),
-                            })
-                            .build(),
-                        )
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Disabled",
-                                DeletePermission::Disabled.as_ref(),
+                        .build(),
+                    )
+                    .build(),
+            )
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(Self::heading("Original link preview", "Change whether the original message's link preview should be kept or removed."))
+            .component(
+                ActionRowBuilder::new()
+                    .component(
+                        Self::hide_original_embed_options(
+                            SelectMenuBuilder::new(
+                                SettingsMenuType::HideOriginalEmbed.as_ref(),
+                                SelectMenuType::Text,
                             )
-                            .default(config.delete_permission == DeletePermission::Disabled)
-                            .description("Delete button no longer appears.")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "🚫".to_string(),
-                            })
-                            .build(),
+                            .max_values(1)
+                            .min_values(1)
+                            .placeholder("Select Original Link Preview Visibility"),
+                            Some(config.hide_original_embed),
                         )
                         .build(),
                     )
@@ -308,46 +637,223 @@ impl SettingsCommand {
                     .spacing(SeparatorSpacingSize::Small)
                     .build(),
             )
-            .component(TextDisplayBuilder::new("### Original link preview").build())
+            .component(Self::heading("Platforms", "Change which platforms the bot fixes links of."))
             .component(
-                TextDisplayBuilder::new(
-                    "Change whether the original message's link preview should be kept or removed.",
-                )
-                .build(),
+                ActionRowBuilder::new()
+                    .component(Self::construct_platform_menu(config))
+                    .build(),
+            )
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(Self::reply_template_section(config))
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(Self::heading("Roles", "Change whose links are fixed (everyone's unless roles are picked, minus the exempt roles), and which roles count as moderators for the delete button."))
+            .component(
+                ActionRowBuilder::new()
+                    .component(Self::construct_role_menu(
+                        SettingsMenuType::AllowedRoles,
+                        "Only fix links of these roles",
+                        &config.allowed_roles,
+                    ))
+                    .build(),
+            )
+            .component(
+                ActionRowBuilder::new()
+                    .component(Self::construct_role_menu(
+                        SettingsMenuType::ExemptRoles,
+                        "Never fix links of these roles",
+                        &config.exempt_roles,
+                    ))
+                    .build(),
             )
+            .component(
+                ActionRowBuilder::new()
+                    .component(Self::construct_role_menu(
+                        SettingsMenuType::ModeratorRoles,
+                        "Moderator roles",
+                        &config.moderator_roles,
+                    ))
+                    .build(),
+            );
+
+        let Some(domain_menu) = Self::construct_domain_menu(config) else {
+            return container.build();
+        };
+
+        container
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(Self::heading(
+                "Embed Providers",
+                "Change which site is used to fix the embeds of each platform.",
+            ))
+            .component(ActionRowBuilder::new().component(domain_menu).build())
+            .build()
+    }
+
+    /// A section's heading along with what it changes.
+    fn heading(title: &str, description: &str) -> Component {
+        Component::TextDisplay(
+            TextDisplayBuilder::new(format!("### {}\n{}", title, description)).build(),
+        )
+    }
+
+    /// The Reply Format section, previewing the current format next to the
+    /// button that opens `construct_reply_template_modal`.
+    fn reply_template_section(config: &ServerConfig) -> Section {
+        let preview = match &config.reply_template {
+            Some(template) => platforms::preview_label(template),
+            None => "Each platform's own format (Default)".to_string(),
+        };
+        let text = format!(
+            "### Reply Format\nChange how the fixed links are written.\n-# Preview: {}",
+            preview
+        );
+        let button = ButtonBuilder::new(ButtonStyle::Secondary)
+            .custom_id(REPLY_TEMPLATE)
+            .label("Edit")
+            .emoji(EmojiReactionType::Unicode {
+                name: "✏️".to_string(),
+            })
+            .build();
+
+        SectionBuilder::new(button)
+            .component(TextDisplayBuilder::new(text).build())
+            .build()
+    }
+
+    /// Returns the modal for editing the reply format.
+    fn construct_reply_template_modal(config: &ServerConfig) -> InteractionResponseData {
+        #[allow(deprecated)] // The label is set on the Label component instead.
+        let input = TextInput {
+            id: None,
+            custom_id: REPLY_TEMPLATE.to_string(),
+            label: None,
+            max_length: Some(platforms::MAX_LABEL_LENGTH as u16),
+            min_length: None,
+            placeholder: Some("Each platform's own format".to_string()),
+            required: Some(false),
+            style: TextInputStyle::Short,
+            value: config.reply_template.clone(),
+        };
+
+        InteractionResponseDataBuilder::new()
+            .custom_id(REPLY_TEMPLATE)
+            .title("Reply Format")
+            .components([
+                Component::TextDisplay(
+                    TextDisplayBuilder::new(
+                        "Use `{author}`, `{platform}`, `{kind}` and `{url}` in the format, which needs a `{url}`. Leave it empty to use each platform's own format.",
+                    )
+                    .build(),
+                ),
+                Component::Label(
+                    LabelBuilder::new("Format", Component::TextInput(input))
+                        .description("Links without a known author keep the platform's format.")
+                        .build(),
+                ),
+            ])
+            .build()
+    }
+
+    /// Returns the Container for overriding the settings of a channel, where
+    /// each menu can also follow the server's setting.
+    fn construct_channel_container(config: &ServerConfig, channel_id: u64) -> Container {
+        let overrides = config
+            .channel_overrides
+            .get(&channel_id)
+            .copied()
+            .unwrap_or_default();
+        let menu = |menu_type: SettingsMenuType, placeholder: &str, inherited: bool| {
+            SelectMenuBuilder::new(
+                format!("{}:{}", menu_type.as_ref(), channel_id),
+                SelectMenuType::Text,
+            )
+            .max_values(1)
+            .min_values(1)
+            .placeholder(placeholder)
+            .option(
+                SelectMenuOptionBuilder::new("Same as server", INHERIT)
+                    .default(inherited)
+                    .description("Follow the server's setting. (Default)")
+                    .emoji(EmojiReactionType::Unicode {
+                        name: "🔗".to_string(),
+                    })
+                    .build(),
+            )
+        };
+
+        ContainerBuilder::new()
+            .spoiler(false)
+            .component(
+                TextDisplayBuilder::new(format!("## Sanitizer Settings for <#{}> 🛠️", channel_id))
+                    .build(),
+            )
+            .component(
+                TextDisplayBuilder::new("Threads in this channel use these settings too.").build(),
+            )
+            .component(
+                SeparatorBuilder::new()
+                    .divider(true)
+                    .spacing(SeparatorSpacingSize::Small)
+                    .build(),
+            )
+            .component(TextDisplayBuilder::new("### Sanitizer Mode").build())
             .component(
                 ActionRowBuilder::new()
                     .component(
-                        SelectMenuBuilder::new(
-                            SettingsMenuType::HideOriginalEmbed.as_ref(),
-                            SelectMenuType::Text,
+                        Self::sanitizer_mode_options(
+                            menu(
+                                SettingsMenuType::SanitizerMode,
+                                "Select Sanitizer Mode",
+                                overrides.sanitizer_mode.is_none(),
+                            ),
+                            overrides.sanitizer_mode,
                         )
-                        .max_values(1)
-                        .min_values(1)
-                        .placeholder("Select Original Link Preview Visibility")
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Keep original preview",
-                                HideOriginalEmbed::Off.as_ref(),
-                            )
-                            .default(!config.hide_original_embed)
-                            .description("Keep the embed of the original message.")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "✅".to_string(),
-                            })
-                            .build(),
+                        .build(),
+                    )
+                    .build(),
+            )
+            .component(TextDisplayBuilder::new("### Delete Button").build())
+            .component(
+                ActionRowBuilder::new()
+                    .component(
+                        Self::delete_permission_options(
+                            menu(
+                                SettingsMenuType::DeletePermission,
+                                "Select Delete Button Permission",
+                                overrides.delete_permission.is_none(),
+                            ),
+                            overrides.delete_permission,
                         )
-                        .option(
-                            SelectMenuOptionBuilder::new(
-                                "Remove original preview",
-                                HideOriginalEmbed::On.as_ref(),
-                            )
-                            .default(config.hide_original_embed)
-                            .description("Hide the embed of the original message. (Default)")
-                            .emoji(EmojiReactionType::Unicode {
-                                name: "❌".to_string(),
-                            })
-                            .build(),
+                        .build(),
+                    )
+                    .build(),
+            )
+            .component(TextDisplayBuilder::new("### Original link preview").build())
+            .component(
+                ActionRowBuilder::new()
+                    .component(
+                        Self::hide_original_embed_options(
+                            menu(
+                                SettingsMenuType::HideOriginalEmbed,
+                                "Select Original Link Preview Visibility",
+                                overrides.hide_original_embed.is_none(),
+                            ),
+                            overrides.hide_original_embed,
                         )
                         .build(),
                     )
@@ -355,4 +861,189 @@ impl SettingsCommand {
             )
             .build()
     }
+
+    /// Adds the Sanitizer Mode options to the menu, marking `selected` as picked.
+    fn sanitizer_mode_options(
+        menu: SelectMenuBuilder,
+        selected: Option<SanitizerMode>,
+    ) -> SelectMenuBuilder {
+        menu.option(
+            SelectMenuOptionBuilder::new("Automatic", SanitizerMode::Automatic.as_ref())
+                .default(selected == Some(SanitizerMode::Automatic))
+                .description("Fix links automatically. (Default)")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "🤖".to_string(),
+                })
+                .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new("Manual: Emote", SanitizerMode::ManualEmote.as_ref())
+                .default(selected == Some(SanitizerMode::ManualEmote))
+                .description("Fix links once a emoji reaction is added.")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "🎭".to_string(),
+                })
+                .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new("Manual: Mention", SanitizerMode::ManualMention.as_ref())
+                .default(selected == Some(SanitizerMode::ManualMention))
+                .description("Fix links in messages mentioning the bot.")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "💬".to_string(),
+                })
+                .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new(
+                "Manual: Mention + Emote",
+                SanitizerMode::ManualBoth.as_ref(),
+            )
+            .default(selected == Some(SanitizerMode::ManualBoth))
+            .description("Fix links either if mentioned or on an emoji reaction.")
+            .emoji(EmojiReactionType::Unicode {
+                name: "🔁".to_string(),
+            })
+            .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new("Repost", SanitizerMode::Repost.as_ref())
+                .default(selected == Some(SanitizerMode::Repost))
+                .description("Replace messages with a copy with fixed links.")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "♻️".to_string(),
+                })
+                .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new("Disabled", SanitizerMode::Disabled.as_ref())
+                .default(selected == Some(SanitizerMode::Disabled))
+                .description("Don't fix any links.")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "🔕".to_string(),
+                })
+                .build(),
+        )
+    }
+
+    /// Adds the Delete Button options to the menu, marking `selected` as picked.
+    fn delete_permission_options(
+        menu: SelectMenuBuilder,
+        selected: Option<DeletePermission>,
+    ) -> SelectMenuBuilder {
+        menu.option(
+            SelectMenuOptionBuilder::new(
+                "Author and Mods",
+                DeletePermission::AuthorAndMods.as_ref(),
+            )
+            .default(selected == Some(DeletePermission::AuthorAndMods))
+            .description("Author or users that can Manage Messages. (Default)")
+            .emoji(EmojiReactionType::Unicode {
+                name: "👥".to_string(),
+            })
+            .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new("Everyone", DeletePermission::Everyone.as_ref())
+                .default(selected == Some(DeletePermission::Everyone))
+                .description("All users, regardless of their permissions.")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "🌐".to_string(),
+                })
+                .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new("Disabled", DeletePermission::Disabled.as_ref())
+                .default(selected == Some(DeletePermission::Disabled))
+                .description("Delete button no longer appears.")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "🚫".to_string(),
+                })
+                .build(),
+        )
+    }
+
+    /// Adds the Original link preview options to the menu, marking `selected`
+    /// (whether the preview is hidden) as picked.
+    fn hide_original_embed_options(
+        menu: SelectMenuBuilder,
+        selected: Option<bool>,
+    ) -> SelectMenuBuilder {
+        menu.option(
+            SelectMenuOptionBuilder::new("Keep original preview", HideOriginalEmbed::Off.as_ref())
+                .default(selected == Some(false))
+                .description("Keep the embed of the original message.")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "✅".to_string(),
+                })
+                .build(),
+        )
+        .option(
+            SelectMenuOptionBuilder::new("Remove original preview", HideOriginalEmbed::On.as_ref())
+                .default(selected == Some(true))
+                .description("Hide the embed of the original message. (Default)")
+                .emoji(EmojiReactionType::Unicode {
+                    name: "❌".to_string(),
+                })
+                .build(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing;
+
+    /// Discord's limit on the number of components in a message, nested ones included.
+    const MAX_COMPONENTS: usize = 40;
+
+    fn count_components(component: &Component) -> usize {
+        1 + match component {
+            Component::Container(container) => {
+                container.components.iter().map(count_components).sum()
+            }
+            Component::ActionRow(row) => row.components.iter().map(count_components).sum(),
+            Component::Section(section) => {
+                section
+                    .components
+                    .iter()
+                    .map(count_components)
+                    .sum::<usize>()
+                    + count_components(&section.accessory)
+            }
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn test_settings_fit_in_one_message() {
+        testing::init_platforms();
+        let config = ServerConfig::new(testing::GUILD_ID);
+
+        for container in [
+            SettingsCommand::construct_container(&config),
+            SettingsCommand::construct_channel_container(&config, 10),
+        ] {
+            let count = count_components(&Component::Container(container));
+            assert!(count <= MAX_COMPONENTS, "{} components", count);
+        }
+    }
+
+    #[test]
+    fn test_platform_options_can_be_told_apart() {
+        testing::init_platforms();
+        let menu = SettingsCommand::construct_platform_menu(&ServerConfig::new(testing::GUILD_ID));
+
+        let mut options = menu
+            .options
+            .unwrap()
+            .into_iter()
+            .map(|option| (option.label, option.description))
+            .collect::<Vec<_>>();
+        let count = options.len();
+        options.sort();
+        options.dedup();
+        assert_eq!(options.len(), count);
+    }
 }
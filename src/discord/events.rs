@@ -11,46 +11,50 @@ use twilight_model::application::interaction::{Interaction, InteractionData};
 use twilight_model::channel::Message;
 use twilight_model::channel::message::{Component, MessageFlags, MessageType};
 use twilight_model::gateway::GatewayReaction;
-use twilight_model::gateway::payload::incoming::MessageDelete;
+use twilight_model::gateway::payload::incoming::{MessageDelete, MessageDeleteBulk};
 use twilight_model::guild::Permissions;
 use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType};
 use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker};
 use twilight_util::builder::InteractionResponseDataBuilder;
 use twilight_util::builder::message::{ContainerBuilder, TextDisplayBuilder};
 
 use crate::db::{MessageAuthor, ResponseMap, ServerConfig};
-use crate::discord::commands;
 use crate::discord::models::{DeletePermission, SanitizerMode, SettingsMenuType};
-use crate::utils::cache;
+use crate::discord::{BotState, commands};
 use crate::{sanitize, utils};
 
+/// Discord only accepts bulk deletes of up to 100 messages.
+const MAX_BULK_DELETE: usize = 100;
+
 /// Handles all types of incomming events from Discord.
-pub async fn handle_event(event: Event, client: Arc<Client>) {
+pub async fn handle_event(event: Event, client: Arc<Client>, state: Arc<BotState>) {
     match event {
         Event::InteractionCreate(ctx) => {
-            if let Err(e) = handle_interaction(ctx.0, &client).await {
+            if let Err(e) = handle_interaction(ctx.0, &client, &state).await {
                 tracing::error!(?e, "Failed to handle Event::InteractionCreate");
             }
         }
         Event::MessageCreate(ctx) => {
+            if is_ignored(&ctx.0) {
+                return;
+            }
+
+            if let Err(e) = handle_on_message(ctx.0, &client, &state).await {
+                tracing::error!(?e, "Failed to handle Event::MessageCreate")
+            }
+        }
+        Event::MessageUpdate(ctx) => {
             // Early exit if message is by the bot itself.
             if crate::BOT_USER_ID
                 .get()
-                .is_some_and(|&bot_id| ctx.0.author.id == bot_id)
-                // Or if the message content has no URL & is not a reply
-                // Note:
-                //      Messages without URL are expected in SanitizerMode::ManualMention,
-                //      such messages can mention the bot in a reply where the message
-                //      referenced (replied to) has a valid URL.
-                || (!utils::contains_url(&ctx.0.content) && !(ctx.0.kind == MessageType::Reply))
-                // Special case: if message contains `—x`, ignore the message completely.
-                || ctx.0.content.contains("—x")
+                .is_some_and(|&bot_id| ctx.author.id == bot_id)
             {
                 return;
             }
 
-            if let Err(e) = handle_on_message(ctx.0, &client).await {
-                tracing::error!(?e, "Failed to handle Event::MessageCreate")
+            if let Err(e) = handle_message_update(ctx.0, &client, &state).await {
+                tracing::error!(?e, "Failed to handle Event::MessageUpdate")
             }
         }
         Event::ReactionAdd(ctx) => {
@@ -63,22 +67,222 @@ pub async fn handle_event(event: Event, client: Arc<Client>) {
                 return;
             }
 
-            if let Err(e) = handle_reaction_add(ctx.0, &client).await {
+            if let Err(e) = handle_reaction_add(ctx.0, &client, &state).await {
                 tracing::error!(?e, "Failed to handle Event::ReactionAdd");
             }
         }
         Event::MessageDelete(ctx) => {
-            if let Err(e) = handle_message_delete(ctx, &client).await {
+            if let Err(e) = handle_message_delete(ctx, &client, &state).await {
                 tracing::error!(?e, "Failed to handle Event::MessageDelete")
             }
         }
+        Event::MessageDeleteBulk(ctx) => {
+            if let Err(e) = handle_message_delete_bulk(ctx, &client, &state).await {
+                tracing::error!(?e, "Failed to handle Event::MessageDeleteBulk")
+            }
+        }
+        // The bot's responses went along with the channel, only the entries remain.
+        Event::ChannelDelete(ctx) => {
+            let Some(guild_id) = ctx.guild_id else {
+                return;
+            };
+            if let Err(e) = handle_channel_delete(guild_id.get(), ctx.id.get(), &state).await {
+                tracing::error!(?e, "Failed to handle Event::ChannelDelete")
+            }
+        }
+        Event::ThreadDelete(ctx) => {
+            state.configs.forget_channel(ctx.id.get());
+            state.webhooks.forget(ctx.id);
+            if let Err(e) = state
+                .responses
+                .delete_channel(ctx.guild_id.get(), ctx.id.get())
+                .await
+            {
+                tracing::error!(?e, "Failed to handle Event::ThreadDelete")
+            }
+        }
+        Event::GuildDelete(ctx) => {
+            // An unavailable guild is an outage, the bot is still a member.
+            if ctx.unavailable == Some(true) {
+                return;
+            }
+            if let Err(e) = state.responses.delete_guild(ctx.id.get()).await {
+                tracing::error!(?e, "Failed to handle Event::GuildDelete")
+            }
+        }
         _ => (),
     }
 }
 
+/// Whether a new message should be skipped without looking any further.
+fn is_ignored(message: &Message) -> bool {
+    // Early exit if message is by the bot itself.
+    crate::BOT_USER_ID
+        .get()
+        .is_some_and(|&bot_id| message.author.id == bot_id)
+        // Or if the message content has no URL & is not a reply
+        // Note:
+        //      Messages without URL are expected in SanitizerMode::ManualMention,
+        //      such messages can mention the bot in a reply where the message
+        //      referenced (replied to) has a valid URL.
+        || (!utils::contains_url(&message.content) && !(message.kind == MessageType::Reply))
+        // Escape for a single message, see `/optout` for all of them.
+        || message.content.contains("—x")
+        // Or if it's a repost sent through the bot's webhook (a bot's
+        // application id is its user id).
+        || message.webhook_id.is_some()
+            && message.application_id.is_some_and(|app_id| {
+                crate::BOT_USER_ID
+                    .get()
+                    .is_some_and(|&bot_id| app_id.cast() == bot_id)
+            })
+}
+
+/// The config that applies in the channel, looking up the parent of threads so
+/// they inherit its overrides.
+async fn channel_config(
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<ServerConfig> {
+    state
+        .configs
+        .get_for_channel(guild_id.get(), channel_id.get(), || async {
+            let channel = client
+                .channel(channel_id)
+                .await?
+                .model()
+                .await
+                .context("Failed to deserialize channel payload from Discord")?;
+
+            Ok(channel
+                .parent_id
+                .filter(|_| channel.kind.is_thread())
+                .map(Id::get))
+        })
+        .await
+}
+
+/// Handles twilight_gateway::Event::ChannelDelete events.
+async fn handle_channel_delete(
+    guild_id: u64,
+    channel_id: u64,
+    state: &BotState,
+) -> anyhow::Result<()> {
+    state.configs.forget_channel(channel_id);
+    state.webhooks.forget(Id::new(channel_id));
+
+    state.responses.delete_channel(guild_id, channel_id).await?;
+
+    let mut config = state.configs.get_or_fetch(guild_id).await?;
+    if config.channel_overrides.remove(&channel_id).is_some() {
+        tracing::debug!(channel_id, "Removing overrides of deleted channel");
+        state.configs.update_config(guild_id, config).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether the author used `/optout`, everywhere or in the message's guild.
+async fn is_opted_out(message: &Message, state: &BotState) -> anyhow::Result<bool> {
+    let opted_out = state
+        .opt_outs
+        .is_opted_out(message.author.id.get(), message.guild_id.map(|id| id.get()))
+        .await?;
+
+    if opted_out {
+        tracing::debug!(message_id = %message.id, "Author opted out");
+    }
+    Ok(opted_out)
+}
+
+/// Handles twilight_gateway::Event::MessageUpdate events.
+async fn handle_message_update(
+    message: Message,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<()> {
+    // Embeds resolving and flag changes (such as suppressing embeds) also fire
+    // updates, only edits to the content are of interest. These keep the
+    // timestamp of the last edit, so each edit is handled once.
+    let Some(edited_at) = message.edited_timestamp else {
+        return Ok(());
+    };
+    if !state
+        .edits
+        .is_new_edit(message.id.get(), edited_at.as_micros())
+    {
+        tracing::debug!(message_id = %message.id, "Edit was already handled");
+        return Ok(());
+    }
+
+    let Some((response_map, MessageAuthor::User)) = state.responses.find_match(message.id).await?
+    else {
+        // Nothing was sent for this message yet, so treat the edit as a new message.
+        tracing::debug!(message_id = %message.id, "No response for edited message");
+        if is_ignored(&message) {
+            return Ok(());
+        }
+        return handle_on_message(message, client, state).await;
+    };
+
+    tracing::debug!(message_id = %message.id, "Response match found for edited message");
+
+    // The `—x` escape can also be edited in to retract the response.
+    if message.content.contains("—x") {
+        return sanitize::delete_reply(client, state.responses.as_ref(), response_map).await;
+    }
+
+    // Opting out leaves the reply as it is, same as new messages being skipped.
+    if is_opted_out(&message, state).await? {
+        return Ok(());
+    }
+
+    let server_config = match message.guild_id {
+        Some(guild_id) => Some(channel_config(guild_id, message.channel_id, client, state).await?),
+        None => None,
+    };
+
+    // Replies sent before the sanitizer was disabled are left as they are.
+    if server_config
+        .as_ref()
+        .is_some_and(|config| config.sanitizer_mode == SanitizerMode::Disabled)
+    {
+        return Ok(());
+    }
+
+    // Same as new messages, authors whose roles aren't sanitized keep the reply
+    // as it is.
+    let roles = message
+        .member
+        .as_ref()
+        .map_or(&[][..], |member| &member.roles);
+    if server_config
+        .as_ref()
+        .is_some_and(|config| !config.is_sanitized_for(roles))
+    {
+        tracing::debug!(message_id = %message.id, "Author's roles are not sanitized");
+        return Ok(());
+    }
+
+    sanitize::update_reply(
+        &message,
+        client,
+        server_config,
+        state.responses.as_ref(),
+        response_map,
+    )
+    .await
+}
+
 /// Handles twilight_gateway::Event::MessageDelete events.
-async fn handle_message_delete(ctx: MessageDelete, client: &Client) -> anyhow::Result<()> {
-    let matched_bot_response = ResponseMap::find_match(ctx.id).await?;
+async fn handle_message_delete(
+    ctx: MessageDelete,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<()> {
+    let matched_bot_response = state.responses.find_match(ctx.id).await?;
 
     let Some((response_map, side)) = matched_bot_response else {
         tracing::debug!(message_id = %ctx.id, "No match found for message");
@@ -88,6 +292,8 @@ async fn handle_message_delete(ctx: MessageDelete, client: &Client) -> anyhow::R
     tracing::debug!(message_id = %ctx.id, "Response match found for message");
 
     match side {
+        // The bot deletes the original itself once it's reposted.
+        MessageAuthor::User if response_map.reposted => return Ok(()),
         // Handles case where the original user message is deleted.
         MessageAuthor::User => {
             if let Err(e) = client
@@ -100,42 +306,119 @@ async fn handle_message_delete(ctx: MessageDelete, client: &Client) -> anyhow::R
                 tracing::debug!(error = ?e, "Bot message already gone (likely race)");
             }
         }
+        // There's no original left to restore once reposted.
+        MessageAuthor::Bot if response_map.reposted => (),
         // Handles case where bot's response is deleted using
         // Discord interface, and not using the custom Delete button.
+        // A failure to restore shouldn't keep the stale entry around.
         MessageAuthor::Bot => {
-            if let Ok(response) = client
-                .message(
-                    Id::new(response_map.channel_id),
-                    Id::new(response_map.user_message_id),
-                )
-                .await
-            {
-                let user_msg = response
-                    .model()
-                    .await
-                    .context("Failed to deserialize user message payload from Discord")?;
+            if let Err(e) = restore_original_embed(&response_map, client).await {
+                tracing::warn!(error = ?e, "Failed to restore original message embed");
+            }
+        }
+    }
+
+    state
+        .responses
+        .delete_response(response_map.user_message_id)
+        .await
+        .with_context(|| format!("Failed to delete response map entry for message {}", ctx.id))?;
 
-                if let Err(e) = utils::unsupress_embeds(&user_msg, client).await {
-                    tracing::debug!(error = ?e, "Failed to unsuppress original message embed");
+    Ok(())
+}
+
+/// Handles twilight_gateway::Event::MessageDeleteBulk events (moderator purges).
+async fn handle_message_delete_bulk(
+    ctx: MessageDeleteBulk,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<()> {
+    let matches = state.responses.find_matches(&ctx.ids).await?;
+    if matches.is_empty() {
+        tracing::debug!(channel_id = %ctx.channel_id, "No matches found for purged messages");
+        return Ok(());
+    }
+
+    tracing::debug!(
+        channel_id = %ctx.channel_id,
+        "Found {} response matches for purged messages",
+        matches.len()
+    );
+
+    // Bot responses to purged messages, unless the purge already got them.
+    let mut responses = Vec::new();
+    for (response_map, side) in &matches {
+        let bot_message_id = Id::new(response_map.bot_message_id);
+        match side {
+            MessageAuthor::User if !ctx.ids.contains(&bot_message_id) => {
+                responses.push(bot_message_id)
+            }
+            MessageAuthor::User => (),
+            MessageAuthor::Bot if response_map.reposted => (),
+            MessageAuthor::Bot => {
+                if let Err(e) = restore_original_embed(response_map, client).await {
+                    tracing::warn!(error = ?e, "Failed to restore original message embed");
                 }
-            } else {
-                tracing::debug!(
-                    user_message_id = response_map.user_message_id,
-                    "User message already deleted or inaccessible, skipping unsupressed"
-                );
             }
         }
     }
 
-    ResponseMap::delete_entry(ctx.id.get())
+    for chunk in responses.chunks(MAX_BULK_DELETE) {
+        let result = match chunk {
+            [message_id] => client.delete_message(ctx.channel_id, *message_id).await,
+            _ => client.delete_messages(ctx.channel_id, chunk).await,
+        };
+        if let Err(e) = result {
+            tracing::debug!(error = ?e, "Failed to delete bot messages (likely race)");
+        }
+    }
+
+    let user_message_ids = matches
+        .iter()
+        .map(|(response_map, _)| response_map.user_message_id)
+        .collect::<Vec<_>>();
+    state
+        .responses
+        .delete_responses(&user_message_ids)
         .await
-        .with_context(|| format!("Failed to delete response map entry for message {}", ctx.id))?;
+        .context("Failed to delete response map entries for purged messages")?;
+
+    Ok(())
+}
+
+/// Unsuppresses the embed of the user message after the bot's response is gone.
+async fn restore_original_embed(response_map: &ResponseMap, client: &Client) -> anyhow::Result<()> {
+    if let Ok(response) = client
+        .message(
+            Id::new(response_map.channel_id),
+            Id::new(response_map.user_message_id),
+        )
+        .await
+    {
+        let user_msg = response
+            .model()
+            .await
+            .context("Failed to deserialize user message payload from Discord")?;
+
+        if let Err(e) = utils::unsupress_embeds(&user_msg, client).await {
+            tracing::debug!(error = ?e, "Failed to unsuppress original message embed");
+        }
+    } else {
+        tracing::debug!(
+            user_message_id = response_map.user_message_id,
+            "User message already deleted or inaccessible, skipping unsupressed"
+        );
+    }
 
     Ok(())
 }
 
 /// Handles twilight_gateway::Event::ReactionAdd events.
-async fn handle_reaction_add(reaction: GatewayReaction, client: &Client) -> anyhow::Result<()> {
+async fn handle_reaction_add(
+    reaction: GatewayReaction,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<()> {
     // Exits early if reaction is not in a guild. This exit should never happen.
     let Some(guild_id) = reaction.guild_id else {
         anyhow::bail!("ReactionAdd is not in a guild.")
@@ -147,48 +430,116 @@ async fn handle_reaction_add(reaction: GatewayReaction, client: &Client) -> anyh
     };
 
     if reaction_emoji_id == *crate::EMOJI_ID.get().unwrap() {
-        let server_config = ServerConfig::get_or_default(guild_id.get()).await?;
+        let server_config = channel_config(guild_id, reaction.channel_id, client, state).await?;
+
+        let roles = reaction
+            .member
+            .as_ref()
+            .map_or(&[][..], |member| &member.roles);
+        if !server_config.is_sanitized_for(roles) {
+            tracing::debug!("Reacting member's roles are not sanitized");
+            return Ok(());
+        }
+
         let message = client
             .message(reaction.channel_id, reaction.message_id)
             .await?
             .model()
             .await?;
 
-        sanitize::process_message(&message, client, Some(server_config)).await?;
+        if is_opted_out(&message, state).await? {
+            return Ok(());
+        }
+
+        sanitize::process_message(
+            &message,
+            client,
+            Some(server_config),
+            state.responses.as_ref(),
+        )
+        .await?;
     }
 
     Ok(())
 }
 
 /// Handles twilight_gateway::Event::MessageCreate events.
-async fn handle_on_message(message: Message, client: &Client) -> anyhow::Result<()> {
+async fn handle_on_message(
+    message: Message,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<()> {
+    if is_opted_out(&message, state).await? {
+        return Ok(());
+    }
+
     // Retrieves guild_id, else early exits.
     let Some(guild_id) = message.guild_id else {
-        sanitize::process_message(&message, client, None).await?;
+        sanitize::process_message(&message, client, None, state.responses.as_ref()).await?;
         return Ok(());
     };
-    let server_config = cache::load().get_or_fetch(guild_id.get()).await?;
+    let server_config = channel_config(guild_id, message.channel_id, client, state).await?;
+
+    let roles = message
+        .member
+        .as_ref()
+        .map_or(&[][..], |member| &member.roles);
+    if !server_config.is_sanitized_for(roles) {
+        tracing::debug!(message_id = %message.id, "Author's roles are not sanitized");
+        return Ok(());
+    }
 
     match server_config.sanitizer_mode {
         SanitizerMode::Automatic => {
-            sanitize::process_message(&message, client, Some(server_config)).await?;
+            sanitize::process_message(
+                &message,
+                client,
+                Some(server_config),
+                state.responses.as_ref(),
+            )
+            .await?;
         }
         SanitizerMode::ManualEmote => {
-            sanitize::add_emote(&message, client).await?;
+            sanitize::add_emote(&message, client, &server_config).await?;
         }
         SanitizerMode::ManualMention => {
             // Early exit if not mentioned.
             if !utils::is_bot_mentioned(&message) && message.kind != MessageType::Reply {
                 return Ok(());
             }
-            sanitize::process_message(&message, client, Some(server_config)).await?;
+            sanitize::process_message(
+                &message,
+                client,
+                Some(server_config),
+                state.responses.as_ref(),
+            )
+            .await?;
         }
         SanitizerMode::ManualBoth => {
-            sanitize::add_emote(&message, client).await?;
+            sanitize::add_emote(&message, client, &server_config).await?;
             if !utils::is_bot_mentioned(&message) && message.kind != MessageType::Reply {
                 return Ok(());
             }
-            sanitize::process_message(&message, client, Some(server_config)).await?;
+            sanitize::process_message(
+                &message,
+                client,
+                Some(server_config),
+                state.responses.as_ref(),
+            )
+            .await?;
+        }
+        SanitizerMode::Repost => {
+            sanitize::repost_message(
+                &message,
+                client,
+                server_config,
+                state.responses.as_ref(),
+                &state.webhooks,
+            )
+            .await?;
+        }
+        SanitizerMode::Disabled => {
+            tracing::debug!("Sanitizer is disabled in this channel");
         }
     }
 
@@ -196,7 +547,11 @@ async fn handle_on_message(message: Message, client: &Client) -> anyhow::Result<
 }
 
 /// Handles twilight_gateway::Event::InteractionCreate events.
-async fn handle_interaction(mut interaction: Interaction, client: &Client) -> anyhow::Result<()> {
+async fn handle_interaction(
+    mut interaction: Interaction,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<()> {
     let Some(data) = interaction.data.take() else {
         tracing::debug!("Ignoring interaction with no data");
         return Ok(());
@@ -206,7 +561,7 @@ async fn handle_interaction(mut interaction: Interaction, client: &Client) -> an
         // Handles command invocations
         InteractionData::ApplicationCommand(data) => {
             tracing::debug!("Recieved ApplicationCommand event with name: {}", data.name);
-            handle_app_command(data.name.as_str(), &interaction, client, &data).await
+            handle_app_command(data.name.as_str(), &interaction, client, &data, state).await
         }
         // Handles component invocations
         InteractionData::MessageComponent(data) => {
@@ -214,7 +569,26 @@ async fn handle_interaction(mut interaction: Interaction, client: &Client) -> an
                 "Recieved MessageComponent event with custom_id: {}",
                 data.custom_id
             );
-            handle_component(&data, &interaction, client).await
+            handle_component(&data, &interaction, client, state).await
+        }
+        // Handles modal submissions
+        InteractionData::ModalSubmit(data) => {
+            tracing::debug!(
+                "Recieved ModalSubmit event with custom_id: {}",
+                data.custom_id
+            );
+            match data.custom_id.as_str() {
+                "reply_template" => {
+                    commands::SettingsCommand::handle_reply_template_modal(
+                        &interaction,
+                        client,
+                        &data,
+                        &state.configs,
+                    )
+                    .await
+                }
+                unknown_id => anyhow::bail!("unknown modal: {}", unknown_id),
+            }
         }
         _ => {
             tracing::debug!("Ignoring unknown interaction type");
@@ -230,12 +604,23 @@ async fn handle_app_command(
     interaction: &Interaction,
     client: &Client,
     data: &CommandData,
+    state: &BotState,
 ) -> anyhow::Result<()> {
     match command_name {
         "help" => commands::HelpCommand::handle(interaction, client).await,
-        "settings" => commands::SettingsCommand::handle(interaction, client).await,
+        "settings" => {
+            commands::SettingsCommand::handle(interaction, client, data, &state.configs).await
+        }
+        "optout" => {
+            commands::OptOutCommand::handle(interaction, client, data, state.opt_outs.as_ref())
+                .await
+        }
+        "optin" => {
+            commands::OptOutCommand::handle_optin(interaction, client, state.opt_outs.as_ref())
+                .await
+        }
         "Sanitize" | "sanitize" => {
-            commands::SanitizeCommand::handle(interaction, client, data).await
+            commands::SanitizeCommand::handle(interaction, client, data, &state.configs).await
         }
         unknown_name => anyhow::bail!("unknown command: {}", unknown_name),
     }
@@ -246,22 +631,51 @@ async fn handle_component(
     data: &MessageComponentInteractionData,
     interaction: &Interaction,
     client: &Client,
+    state: &BotState,
 ) -> anyhow::Result<()> {
-    match data.custom_id.as_str() {
-        "delete" => handle_delete_button(interaction, client).await,
-        _ => {
-            let menu_type = data
-                .custom_id
+    // Menus for the overrides of a channel carry its id, e.g. `sanitizer_mode:123`,
+    // and delete buttons on reposts the author's, e.g. `delete:456`.
+    let (component_id, target_id) = match data.custom_id.split_once(':') {
+        Some((component_id, target_id)) => (component_id, Some(target_id.parse::<u64>()?)),
+        None => (data.custom_id.as_str(), None),
+    };
+
+    match component_id {
+        "delete" => handle_delete_button(interaction, target_id, client, state).await,
+        "reply_template" => {
+            commands::SettingsCommand::handle_reply_template_button(
+                interaction,
+                client,
+                &state.configs,
+            )
+            .await
+        }
+        menu_id => {
+            let menu_type = menu_id
                 .parse::<SettingsMenuType>()
                 .with_context(|| format!("Unknown component: {}", data.custom_id))?;
-            commands::SettingsCommand::handle_component(interaction, menu_type, data, client).await
+            commands::SettingsCommand::handle_component(
+                interaction,
+                menu_type,
+                target_id,
+                data,
+                client,
+                &state.configs,
+            )
+            .await
         }
     }
 }
 
 /// These handles delete button for both Interaction & OnMessage invocation of Sanitize.
 /// Expected to be called ONLY in Guild Context, NOT in PrivateChannel Context.
-async fn handle_delete_button(interaction: &Interaction, client: &Client) -> anyhow::Result<()> {
+/// `repost_author_id` is set for reposts, which don't reference the author's message.
+async fn handle_delete_button(
+    interaction: &Interaction,
+    repost_author_id: Option<u64>,
+    client: &Client,
+    state: &BotState,
+) -> anyhow::Result<()> {
     let Some(ref bot_msg) = interaction.message else {
         anyhow::bail!("Delete button pressed but no message found")
     };
@@ -277,7 +691,7 @@ async fn handle_delete_button(interaction: &Interaction, client: &Client) -> any
     let Some(guild_id) = interaction.guild_id else {
         anyhow::bail!("Interaction missing guild_id")
     };
-    let server_config = ServerConfig::get_or_default(guild_id.get()).await?;
+    let server_config = channel_config(guild_id, bot_msg.channel_id, client, state).await?;
 
     if server_config.delete_permission == DeletePermission::Disabled {
         tracing::debug!("Early exit: Delete button is disabled in server config");
@@ -293,7 +707,10 @@ async fn handle_delete_button(interaction: &Interaction, client: &Client) -> any
         DeletePermission::Everyone => true,
         DeletePermission::Disabled => false,
         DeletePermission::AuthorAndMods => {
-            let author_id = msg.referenced_message.as_ref().map(|msg| msg.author.id);
+            let author_id = match repost_author_id {
+                Some(author_id) => Some(Id::new(author_id)),
+                None => msg.referenced_message.as_ref().map(|msg| msg.author.id),
+            };
 
             let is_author = author_id.is_some_and(|author_id| author_id == invoker_id);
 
@@ -303,7 +720,12 @@ async fn handle_delete_button(interaction: &Interaction, client: &Client) -> any
                 .and_then(|m| m.permissions)
                 .is_some_and(|perms| perms.contains(Permissions::MANAGE_MESSAGES));
 
-            is_author || has_manage_message
+            let has_moderator_role = interaction
+                .member
+                .as_ref()
+                .is_some_and(|m| server_config.is_moderator(&m.roles));
+
+            is_author || has_manage_message || has_moderator_role
         }
     };
 
@@ -351,10 +773,24 @@ async fn handle_delete_button(interaction: &Interaction, client: &Client) -> any
         }
     }
 
-    if let Some(referenced_message) = msg.referenced_message.as_ref() {
+    if repost_author_id.is_some() {
+        // The original is gone, so there's only the entry to remove.
+        if let Some((response_map, _)) = state.responses.find_match(bot_msg.id).await?
+            && let Err(e) = state
+                .responses
+                .delete_response(response_map.user_message_id)
+                .await
+        {
+            tracing::warn!(error = ?e, "Failed to delete response_map entry");
+        }
+    } else if let Some(referenced_message) = msg.referenced_message.as_ref() {
         // Removes the now-stale response_map entry so a later deletion of the
         // original message doesn't try to re-delete this already-deleted bot message.
-        if let Err(e) = ResponseMap::delete_entry(referenced_message.id.get()).await {
+        if let Err(e) = state
+            .responses
+            .delete_response(referenced_message.id.get())
+            .await
+        {
             tracing::warn!(error = ?e, "Failed to delete response_map entry");
         }
 
@@ -368,3 +804,298 @@ async fn handle_delete_button(interaction: &Interaction, client: &Client) -> any
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::db::{MemoryStore, OptOut, OptOutStore, ResponseStore};
+    use crate::utils::testing::{self, GUILD_ID, StubRequest};
+
+    const CHANNEL_ID: u64 = 10;
+    const USER_MESSAGE_ID: u64 = 20;
+    const BOT_MESSAGE_ID: u64 = 30;
+    const AUTHOR_ID: u64 = 2;
+
+    /// The bot's response as fetched from Discord, replying to the user's message.
+    fn bot_message_json() -> serde_json::Value {
+        let mut bot_message = testing::message_json(BOT_MESSAGE_ID, CHANNEL_ID, 1, "fixed");
+        bot_message["type"] = json!(19);
+        bot_message["referenced_message"] =
+            testing::message_json(USER_MESSAGE_ID, CHANNEL_ID, AUTHOR_ID, "link");
+        bot_message
+    }
+
+    /// A press of the delete button under the bot's response.
+    fn delete_button_press(invoker_id: u64, roles: &[u64]) -> Interaction {
+        let roles = roles.iter().map(u64::to_string).collect::<Vec<_>>();
+        serde_json::from_value(json!({
+            "id": "40",
+            "application_id": "50",
+            "type": 3,
+            "token": "token",
+            "version": 1,
+            "guild_id": GUILD_ID.to_string(),
+            "channel_id": CHANNEL_ID.to_string(),
+            "member": {
+                "user": testing::user_json(invoker_id),
+                "roles": roles,
+                "joined_at": "2026-01-01T00:00:00.000000+00:00",
+                "deaf": false,
+                "mute": false,
+                "flags": 0,
+                "permissions": "0",
+            },
+            "message": bot_message_json(),
+            "data": { "custom_id": "delete", "component_type": 2 },
+            "entitlements": [],
+            "authorizing_integration_owners": {},
+        }))
+        .unwrap()
+    }
+
+    fn discord_stub(request: &StubRequest) -> String {
+        match request.method.as_str() {
+            "GET" => testing::json_response(&bot_message_json()),
+            "PATCH" => testing::json_response(&testing::message_json(
+                USER_MESSAGE_ID,
+                CHANNEL_ID,
+                AUTHOR_ID,
+                "link",
+            )),
+            _ => testing::empty_response(),
+        }
+    }
+
+    async fn state_with_response() -> (Arc<MemoryStore>, BotState) {
+        let store = Arc::new(MemoryStore::default());
+        let user_message = testing::message(USER_MESSAGE_ID, CHANNEL_ID, AUTHOR_ID, "link");
+        store
+            .save_response(&ResponseMap::new(&user_message, Id::new(BOT_MESSAGE_ID)))
+            .await
+            .unwrap();
+
+        let state = BotState::new(store.clone(), store.clone(), store.clone());
+        (store, state)
+    }
+
+    #[tokio::test]
+    async fn test_delete_button_by_author() {
+        let (addr, requests) = testing::spawn_stub_server(discord_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        handle_delete_button(&delete_button_press(AUTHOR_ID, &[]), None, &client, &state)
+            .await
+            .unwrap();
+
+        let requests = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| format!("{} {}", request.method, request.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requests,
+            [
+                format!(
+                    "GET /api/v10/channels/{}/messages/{}",
+                    CHANNEL_ID, BOT_MESSAGE_ID
+                ),
+                format!(
+                    "DELETE /api/v10/channels/{}/messages/{}",
+                    CHANNEL_ID, BOT_MESSAGE_ID
+                ),
+                // Unsuppresses the embed of the original message.
+                format!(
+                    "PATCH /api/v10/channels/{}/messages/{}",
+                    CHANNEL_ID, USER_MESSAGE_ID
+                ),
+            ]
+        );
+        assert!(store.responses().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_button_without_permission() {
+        let (addr, requests) = testing::spawn_stub_server(discord_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        handle_delete_button(
+            &delete_button_press(AUTHOR_ID + 1, &[]),
+            None,
+            &client,
+            &state,
+        )
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].method, "POST");
+        assert_eq!(requests[1].path, "/api/v10/interactions/40/token/callback");
+        assert!(requests[1].body.contains("You do not have permission"));
+        assert_eq!(store.responses().len(), 1);
+    }
+
+    /// The user's message after an edit to a new link.
+    fn edited_message() -> Message {
+        let mut message = testing::message_json(
+            USER_MESSAGE_ID,
+            CHANNEL_ID,
+            AUTHOR_ID,
+            "https://x.com/someone/status/123",
+        );
+        message["edited_timestamp"] = json!("2026-01-01T00:01:00.000000+00:00");
+        serde_json::from_value(message).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_repeated_update_for_same_edit() {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(discord_stub);
+        let client = testing::discord_client(addr);
+        let (_store, state) = state_with_response().await;
+
+        handle_message_update(edited_message(), &client, &state)
+            .await
+            .unwrap();
+        let handled = requests.lock().unwrap().len();
+        assert!(handled > 0);
+
+        // The embed resolving for the edited link.
+        handle_message_update(edited_message(), &client, &state)
+            .await
+            .unwrap();
+        assert_eq!(requests.lock().unwrap().len(), handled);
+    }
+
+    #[tokio::test]
+    async fn test_unsanitized_author_edit_keeps_reply() {
+        let (addr, requests) = testing::spawn_stub_server(discord_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        let mut config = ServerConfig::new(GUILD_ID);
+        config.allowed_roles.insert(60);
+        state.configs.update_config(GUILD_ID, config).await.unwrap();
+
+        handle_message_update(edited_message(), &client, &state)
+            .await
+            .unwrap();
+
+        assert!(requests.lock().unwrap().is_empty());
+        assert_eq!(store.responses().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_opted_out_author_edit_keeps_reply() {
+        let (addr, requests) = testing::spawn_stub_server(discord_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        let opt_out = OptOut {
+            user_id: AUTHOR_ID,
+            guild_id: None,
+        };
+        store.save_opt_out(&opt_out).await.unwrap();
+
+        handle_message_update(edited_message(), &client, &state)
+            .await
+            .unwrap();
+
+        assert!(requests.lock().unwrap().is_empty());
+        assert_eq!(store.responses().len(), 1);
+    }
+
+    /// Discord answering with a user message that can't be read.
+    fn broken_message_stub(_request: &StubRequest) -> String {
+        testing::json_response(&json!({}))
+    }
+
+    #[tokio::test]
+    async fn test_delete_keeps_going_when_restore_fails() {
+        let (addr, _requests) = testing::spawn_stub_server(broken_message_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        let ctx = MessageDelete {
+            channel_id: Id::new(CHANNEL_ID),
+            guild_id: Some(Id::new(GUILD_ID)),
+            id: Id::new(BOT_MESSAGE_ID),
+        };
+        handle_message_delete(ctx, &client, &state).await.unwrap();
+
+        assert!(store.responses().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_purge_keeps_going_when_restore_fails() {
+        let (addr, _requests) = testing::spawn_stub_server(broken_message_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        let ctx = MessageDeleteBulk {
+            channel_id: Id::new(CHANNEL_ID),
+            guild_id: Some(Id::new(GUILD_ID)),
+            ids: vec![Id::new(BOT_MESSAGE_ID)],
+        };
+        handle_message_delete_bulk(ctx, &client, &state)
+            .await
+            .unwrap();
+
+        assert!(store.responses().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_button_by_moderator_role() {
+        const MODERATOR_ROLE_ID: u64 = 60;
+
+        let (addr, requests) = testing::spawn_stub_server(discord_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        let mut config = ServerConfig::new(GUILD_ID);
+        config.moderator_roles.insert(MODERATOR_ROLE_ID);
+        state.configs.update_config(GUILD_ID, config).await.unwrap();
+
+        handle_delete_button(
+            &delete_button_press(AUTHOR_ID + 1, &[MODERATOR_ROLE_ID]),
+            None,
+            &client,
+            &state,
+        )
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[1].method, "DELETE");
+        assert!(store.responses().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_opted_out_author_gets_no_reply() {
+        let (addr, requests) = testing::spawn_stub_server(discord_stub);
+        let client = testing::discord_client(addr);
+        let (store, state) = state_with_response().await;
+
+        let opt_out = OptOut {
+            user_id: AUTHOR_ID,
+            guild_id: Some(GUILD_ID),
+        };
+        store.save_opt_out(&opt_out).await.unwrap();
+
+        let message = testing::message(
+            USER_MESSAGE_ID + 1,
+            CHANNEL_ID,
+            AUTHOR_ID,
+            "https://x.com/someone/status/123",
+        );
+        handle_on_message(message, &client, &state).await.unwrap();
+
+        assert!(requests.lock().unwrap().is_empty());
+        assert_eq!(store.responses().len(), 1);
+    }
+}
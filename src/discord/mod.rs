@@ -2,5 +2,9 @@ pub mod commands;
 pub mod models;
 
 mod events;
+mod state;
+mod webhooks;
 
 pub use events::handle_event;
+pub use state::BotState;
+pub use webhooks::WebhookCache;
@@ -7,6 +7,25 @@ pub enum SettingsMenuType {
     SanitizerMode,
     DeletePermission,
     HideOriginalEmbed,
+    ReplacementDomains,
+    EnabledPlatforms,
+    AllowedRoles,
+    ExemptRoles,
+    ModeratorRoles,
+}
+
+impl SettingsMenuType {
+    /// Whether the menu takes any number of values, including none.
+    pub fn is_multi_select(self) -> bool {
+        matches!(
+            self,
+            Self::ReplacementDomains
+                | Self::EnabledPlatforms
+                | Self::AllowedRoles
+                | Self::ExemptRoles
+                | Self::ModeratorRoles
+        )
+    }
 }
 
 impl AsRef<str> for SettingsMenuType {
@@ -15,6 +34,11 @@ impl AsRef<str> for SettingsMenuType {
             Self::SanitizerMode => "sanitizer_mode",
             Self::DeletePermission => "delete_permission",
             Self::HideOriginalEmbed => "hide_original_embed",
+            Self::ReplacementDomains => "replacement_domains",
+            Self::EnabledPlatforms => "enabled_platforms",
+            Self::AllowedRoles => "allowed_roles",
+            Self::ExemptRoles => "exempt_roles",
+            Self::ModeratorRoles => "moderator_roles",
         }
     }
 }
@@ -27,6 +51,11 @@ impl FromStr for SettingsMenuType {
             "sanitizer_mode" => Ok(Self::SanitizerMode),
             "delete_permission" => Ok(Self::DeletePermission),
             "hide_original_embed" => Ok(Self::HideOriginalEmbed),
+            "replacement_domains" => Ok(Self::ReplacementDomains),
+            "enabled_platforms" => Ok(Self::EnabledPlatforms),
+            "allowed_roles" => Ok(Self::AllowedRoles),
+            "exempt_roles" => Ok(Self::ExemptRoles),
+            "moderator_roles" => Ok(Self::ModeratorRoles),
             _ => Err(anyhow::anyhow!("Unknown settings menu type: {}", s)),
         }
     }
@@ -41,6 +70,8 @@ pub enum SanitizerMode {
     ManualEmote = 1,
     ManualMention = 2,
     ManualBoth = 3,
+    Disabled = 4,
+    Repost = 5,
 }
 
 impl From<i32> for SanitizerMode {
@@ -50,6 +81,8 @@ impl From<i32> for SanitizerMode {
             1 => SanitizerMode::ManualEmote,
             2 => SanitizerMode::ManualMention,
             3 => SanitizerMode::ManualBoth,
+            4 => SanitizerMode::Disabled,
+            5 => SanitizerMode::Repost,
             _ => SanitizerMode::Automatic, // Default
         }
     }
@@ -62,6 +95,8 @@ impl AsRef<str> for SanitizerMode {
             Self::ManualEmote => "manual_emote",
             Self::ManualMention => "manual_mention",
             Self::ManualBoth => "manual_both",
+            Self::Disabled => "disabled",
+            Self::Repost => "repost",
         }
     }
 }
@@ -75,6 +110,8 @@ impl FromStr for SanitizerMode {
             "manual_emote" => Ok(Self::ManualEmote),
             "manual_mention" => Ok(Self::ManualMention),
             "manual_both" => Ok(Self::ManualBoth),
+            "disabled" => Ok(Self::Disabled),
+            "repost" => Ok(Self::Repost),
             _ => Err(anyhow::anyhow!("Unknown sanitizer mode: {}", s)),
         }
     }
@@ -166,6 +203,8 @@ mod tests {
         assert_eq!(SanitizerMode::ManualEmote.as_ref(), "manual_emote");
         assert_eq!(SanitizerMode::ManualMention.as_ref(), "manual_mention");
         assert_eq!(SanitizerMode::ManualBoth.as_ref(), "manual_both");
+        assert_eq!(SanitizerMode::Disabled.as_ref(), "disabled");
+        assert_eq!(SanitizerMode::Repost.as_ref(), "repost");
 
         // Test parsing from component IDs using FromStr
         assert_eq!(
@@ -184,6 +223,14 @@ mod tests {
             "manual_both".parse::<SanitizerMode>().unwrap(),
             SanitizerMode::ManualBoth
         );
+        assert_eq!(
+            "disabled".parse::<SanitizerMode>().unwrap(),
+            SanitizerMode::Disabled
+        );
+        assert_eq!(
+            "repost".parse::<SanitizerMode>().unwrap(),
+            SanitizerMode::Repost
+        );
     }
 
     #[test]
@@ -237,6 +284,14 @@ mod tests {
             SettingsMenuType::HideOriginalEmbed.as_ref(),
             "hide_original_embed"
         );
+        assert_eq!(
+            SettingsMenuType::ReplacementDomains.as_ref(),
+            "replacement_domains"
+        );
+        assert_eq!(
+            SettingsMenuType::EnabledPlatforms.as_ref(),
+            "enabled_platforms"
+        );
 
         // Test parsing from component IDs using FromStr
         assert_eq!(
@@ -251,6 +306,18 @@ mod tests {
             "hide_original_embed".parse::<SettingsMenuType>().unwrap(),
             SettingsMenuType::HideOriginalEmbed
         );
+        assert_eq!(
+            "replacement_domains".parse::<SettingsMenuType>().unwrap(),
+            SettingsMenuType::ReplacementDomains
+        );
+        assert_eq!(
+            "enabled_platforms".parse::<SettingsMenuType>().unwrap(),
+            SettingsMenuType::EnabledPlatforms
+        );
+        assert_eq!(
+            "moderator_roles".parse::<SettingsMenuType>().unwrap(),
+            SettingsMenuType::ModeratorRoles
+        );
     }
 
     #[test]
@@ -263,6 +330,8 @@ mod tests {
             SanitizerMode::ManualEmote,
             SanitizerMode::ManualMention,
             SanitizerMode::ManualBoth,
+            SanitizerMode::Disabled,
+            SanitizerMode::Repost,
         ] {
             let id = variant.as_ref();
             let parsed = id.parse::<SanitizerMode>().unwrap();
@@ -315,6 +384,8 @@ mod tests {
         assert_eq!(SanitizerMode::from(1), SanitizerMode::ManualEmote);
         assert_eq!(SanitizerMode::from(2), SanitizerMode::ManualMention);
         assert_eq!(SanitizerMode::from(3), SanitizerMode::ManualBoth);
+        assert_eq!(SanitizerMode::from(4), SanitizerMode::Disabled);
+        assert_eq!(SanitizerMode::from(5), SanitizerMode::Repost);
         assert_eq!(SanitizerMode::from(999), SanitizerMode::Automatic); // Invalid value should default
 
         assert_eq!(DeletePermission::from(0), DeletePermission::AuthorAndMods);
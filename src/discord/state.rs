@@ -0,0 +1,31 @@
+//! State shared by every event handler.
+
+use std::sync::Arc;
+
+use crate::db::{ConfigStore, OptOutStore, ResponseStore};
+use crate::discord::WebhookCache;
+use crate::utils::cache::{ConfigCache, EditCache};
+
+pub struct BotState {
+    pub configs: ConfigCache,
+    pub responses: Arc<dyn ResponseStore>,
+    pub opt_outs: Arc<dyn OptOutStore>,
+    pub edits: EditCache,
+    pub webhooks: WebhookCache,
+}
+
+impl BotState {
+    pub fn new(
+        configs: Arc<dyn ConfigStore>,
+        responses: Arc<dyn ResponseStore>,
+        opt_outs: Arc<dyn OptOutStore>,
+    ) -> Self {
+        Self {
+            configs: ConfigCache::new(configs),
+            responses,
+            opt_outs,
+            edits: EditCache::default(),
+            webhooks: WebhookCache::default(),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Webhooks used to repost messages in `SanitizerMode::Repost`, one per channel.
+
+use anyhow::Context;
+use dashmap::DashMap;
+use twilight_http::Client;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, WebhookMarker};
+
+/// The name of the webhooks the bot creates, so they can be found again.
+const WEBHOOK_NAME: &str = "Sanitizer";
+
+/// A webhook that can post into a channel.
+#[derive(Debug, Clone)]
+pub struct ChannelWebhook {
+    pub id: Id<WebhookMarker>,
+    pub token: String,
+    /// Set when posting into a thread, since webhooks belong to its parent.
+    pub thread_id: Option<Id<ChannelMarker>>,
+}
+
+/// Caches the bot's webhook for each channel it reposted in.
+#[derive(Default)]
+pub struct WebhookCache {
+    webhooks: DashMap<Id<ChannelMarker>, ChannelWebhook>,
+}
+
+impl WebhookCache {
+    /// Gets the webhook for a channel (or thread), reusing the bot's existing
+    /// webhook in the channel or creating one.
+    pub async fn get_or_create(
+        &self,
+        client: &Client,
+        channel_id: Id<ChannelMarker>,
+    ) -> anyhow::Result<ChannelWebhook> {
+        if let Some(webhook) = self.webhooks.get(&channel_id) {
+            return Ok(webhook.clone());
+        }
+
+        let channel = client.channel(channel_id).await?.model().await?;
+        let (parent_id, thread_id) = match channel.parent_id {
+            Some(parent_id) if channel.kind.is_thread() => (parent_id, Some(channel_id)),
+            _ => (channel_id, None),
+        };
+
+        let bot_user_id = crate::BOT_USER_ID.get().copied();
+        let existing = client
+            .channel_webhooks(parent_id)
+            .await?
+            .models()
+            .await?
+            .into_iter()
+            .find(|webhook| {
+                webhook.name.as_deref() == Some(WEBHOOK_NAME)
+                    && webhook.token.is_some()
+                    && webhook.user.as_ref().map(|user| user.id) == bot_user_id
+            });
+
+        let webhook = match existing {
+            Some(webhook) => webhook,
+            None => {
+                tracing::debug!(channel_id = %parent_id, "Creating webhook");
+                client
+                    .create_webhook(parent_id, WEBHOOK_NAME)
+                    .await?
+                    .model()
+                    .await?
+            }
+        };
+
+        let webhook = ChannelWebhook {
+            id: webhook.id,
+            token: webhook.token.context("Webhook has no token")?,
+            thread_id,
+        };
+        self.webhooks.insert(channel_id, webhook.clone());
+
+        Ok(webhook)
+    }
+
+    /// Forgets the webhook of a channel, e.g. after it was deleted.
+    pub fn forget(&self, channel_id: Id<ChannelMarker>) {
+        self.webhooks.remove(&channel_id);
+    }
+}
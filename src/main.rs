@@ -22,8 +22,9 @@ use twilight_model::gateway::presence::{ActivityType, MinimalActivity, Status};
 use twilight_model::id::Id;
 use twilight_model::id::marker::{EmojiMarker, UserMarker};
 
-use crate::discord::{commands, handle_event};
-use crate::utils::cache::ConfigCache;
+use crate::db::TursoStore;
+use crate::discord::{BotState, commands, handle_event};
+use crate::sanitize::{PlatformRegistry, health_worker};
 
 // Flag that can be checked by any part of the program.
 static SHUTDOWN: AtomicBool = AtomicBool::new(false);
@@ -31,8 +32,8 @@ static SHUTDOWN: AtomicBool = AtomicBool::new(false);
 static BOT_USER_ID: OnceLock<Id<UserMarker>> = OnceLock::new();
 // Custom Emoji ID.
 static EMOJI_ID: OnceLock<Id<EmojiMarker>> = OnceLock::new();
-// Cache for server config.
-static CONFIG_CACHE: OnceLock<ConfigCache> = OnceLock::new();
+// Supported platforms, loaded once during pre-run.
+static PLATFORM_REGISTRY: OnceLock<PlatformRegistry> = OnceLock::new();
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -59,7 +60,9 @@ async fn run() -> anyhow::Result<()> {
         .set(Id::<EmojiMarker>::new(emoji_id))
         .expect("EMOJI_ID already initialized");
 
-    let intents = Intents::GUILD_MESSAGES
+    // GUILDS is needed for channel, thread & guild deletes.
+    let intents = Intents::GUILDS
+        | Intents::GUILD_MESSAGES
         | Intents::DIRECT_MESSAGES
         | Intents::MESSAGE_CONTENT
         | Intents::GUILD_MESSAGE_REACTIONS;
@@ -93,12 +96,25 @@ async fn run() -> anyhow::Result<()> {
         .expect("BOT_USER_ID already initialized");
     tracing::info!("{} online with ID: {}", bot.name, bot.id);
 
-    // Initialize cache
-    CONFIG_CACHE
-        .set(ConfigCache::new())
-        .expect("CONFIG_CACHE already initialized");
+    // Initialize the stores behind the event handlers, along with the config cache.
+    let store = Arc::new(TursoStore);
+    let state = Arc::new(BotState::new(store.clone(), store.clone(), store));
     tracing::info!("Config cache initialized");
 
+    // Drops cached configs changed by other instances whenever changes are pulled.
+    let invalidation_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            db::wait_for_pull().await;
+            if let Err(e) = invalidation_state.configs.invalidate_changed().await {
+                tracing::warn!("Failed to invalidate changed server configs: {:?}", e);
+            }
+        }
+    });
+
+    // Start probing fixer domains so dead ones can be skipped.
+    tokio::spawn(health_worker());
+
     // Start gateway shards.
     let shards =
         twilight_gateway::create_recommended(&client, config, |_id, builder| builder.build())
@@ -109,7 +125,11 @@ async fn run() -> anyhow::Result<()> {
 
     for shard in shards {
         senders.push(shard.sender());
-        tasks.push(tokio::spawn(shard_runner(shard, client.clone())));
+        tasks.push(tokio::spawn(shard_runner(
+            shard,
+            client.clone(),
+            state.clone(),
+        )));
     }
 
     // Handle exiting Ctrl+C gracefully.
@@ -128,11 +148,14 @@ async fn run() -> anyhow::Result<()> {
         _ = join_handle.await;
     }
 
+    // Make sure the remote has every write before exiting.
+    db::flush().await;
+
     Ok(())
 }
 
 /// Handles shards.
-async fn shard_runner(mut shard: Shard, client: Arc<Client>) {
+async fn shard_runner(mut shard: Shard, client: Arc<Client>, state: Arc<BotState>) {
     // Runs until next_event returns None.
     while let Some(item) = shard.next_event(EventTypeFlags::all()).await {
         let event = match item {
@@ -146,7 +169,7 @@ async fn shard_runner(mut shard: Shard, client: Arc<Client>) {
 
         // Process Discord events (see `discord::events.rs` for implementation).
         tracing::debug!(kind = ?event.kind(), shard = ?shard.id().number(), "received event");
-        tokio::spawn(handle_event(event, client.clone()));
+        tokio::spawn(handle_event(event, client.clone(), state.clone()));
     }
 }
 
@@ -171,6 +194,12 @@ async fn prerun_init() -> anyhow::Result<()> {
 
     tracing::debug!("Logging initialized");
 
+    let registry = PlatformRegistry::load()?;
+    tracing::info!("Loaded {} supported platforms", registry.len());
+    PLATFORM_REGISTRY
+        .set(registry)
+        .expect("PLATFORM_REGISTRY already initialized");
+
     db::init().await?;
 
     Ok(())
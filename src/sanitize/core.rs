@@ -1,243 +1,99 @@
-use std::sync::LazyLock;
-
-use anyhow::{Context, Ok};
-use regex::{Regex, RegexSet, RegexSetBuilder};
-
-// Regex's for capturing urls.
-const INSTAGRAM_URL_PATTERN: &str =
-    r"(?i)https?://(?:www\.)?instagram\.com/(?P<type>reels?|p)(?P<data>/[^/\s?)\]`|]+)";
-
-const REDDIT_URL_PATTERN: &str = r#"(?xi)
-    https?://
-    (?P<subdomain>(?:www\.|old\.|np\.|amp\.)?)
-    reddit\.com/
-    (?P<subreddit>r/[^/]+)
-    (?P<data>
-        /comments/[^/?\s)\]}>,.!;:'"`|]+(?:/[^?\s)\]}>,.!;:'"`|]*)*
-      |
-        /s/[^/?\s)\]}>,.!;:'"`|]+
-    )
-"#;
-
-const TIKTOK_URL_PATTERN: &str =
-    r"(?i)https?://(?P<subdomain>(?:\w{1,3}\.)?)(?P<domain>tiktok\.com)(?P<data>/[^?\s)\]`|]*)";
-
-const TWITTER_URL_PATTERN: &str =
-    r"(?i)https?://(www\.)?(twitter|x)\.com/(?P<username>\w+)(?P<data>/status/[^?\s)\]`|]*)";
-
-// const TWITCH_URL_PATTERN: &str = r"(?i)https?://(www\.)?(twitch\.tv/(?P<username>\w+)/clip/|clips\.twitch\.tv/)(?P<data>[^?\s)\]`|]+)";
-
-const URL_PATTERNS: &[&str] = &[
-    INSTAGRAM_URL_PATTERN,
-    REDDIT_URL_PATTERN,
-    TIKTOK_URL_PATTERN,
-    TWITTER_URL_PATTERN,
-    // TWITCH_URL_PATTERN,
-];
-
-static INDIVIDUAL_REGEXES: LazyLock<[Regex; 4]> = LazyLock::new(|| {
-    [
-        Regex::new(INSTAGRAM_URL_PATTERN).expect("Valid Instagram regex"),
-        Regex::new(REDDIT_URL_PATTERN).expect("Valid Reddit regex"),
-        Regex::new(TIKTOK_URL_PATTERN).expect("Valid TikTok regex"),
-        Regex::new(TWITTER_URL_PATTERN).expect("Valid Twitter regex"),
-        // Regex::new(TWITCH_URL_PATTERN).expect("Valid Twitch regex"),
-    ]
-});
-static REGEX_SET: LazyLock<anyhow::Result<RegexSet>> = LazyLock::new(|| {
-    RegexSetBuilder::new(URL_PATTERNS)
-        .case_insensitive(true)
-        .multi_line(true)
-        .build()
-        .context("CRITICAL: Failed to build RegexSet")
-});
-
-#[derive(Debug, Clone, Copy)]
-enum Platform {
-    Instagram = 0,
-    Reddit = 1,
-    TikTok = 2,
-    Twitter = 3,
-    // Twitch = 3,
-}
-
-impl Platform {
-    fn try_detect(input: &str) -> Option<Self> {
-        let regex_set = REGEX_SET.as_ref().ok()?;
-        let matches = regex_set.matches(input);
-        tracing::debug!("Trying to detect a match in the url.");
+use scraper::Selector;
+use serde::Deserialize;
 
-        matches.iter().next().and_then(Self::from_index)
-    }
+use crate::db::ServerConfig;
+use crate::sanitize::platforms::{self, AuthorResolver, Platform};
+use crate::sanitize::{health, http};
 
-    const fn display_name(&self) -> &'static str {
-        match self {
-            Self::Instagram => "Instagram",
-            Self::Reddit => "Reddit",
-            Self::TikTok => "TikTok",
-            Self::Twitter => "Twitter",
-            // Self::Twitch => "Twitch",
-        }
-    }
+const BLUESKY_PROFILE_API: &str = "https://public.api.bsky.app/xrpc/app.bsky.actor.getProfile";
 
-    const fn from_index(idx: usize) -> Option<Self> {
-        match idx {
-            0 => Some(Self::Instagram),
-            1 => Some(Self::Reddit),
-            2 => Some(Self::TikTok),
-            3 => Some(Self::Twitter),
-            // 4 => Some(Self::Twitch),
-            _ => None,
-        }
-    }
-
-    const fn replacement_domain(&self) -> &'static str {
-        match self {
-            Self::Instagram => "kkinstagram.com",
-            Self::Reddit => "vxreddit.com",
-            Self::TikTok => "kktiktok.com",
-            Self::Twitter => "fxtwitter.com",
-            // Self::Twitch => "fxtwitch.seria.moe",
-        }
-    }
+/// The subset of `app.bsky.actor.getProfile` needed to resolve a handle.
+#[derive(Debug, Deserialize)]
+struct BlueskyProfile {
+    handle: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct UrlProcessor {
-    platform: Platform,
+    platform: &'static Platform,
     user_input: String, // Gets set in caller
     clean_url: Option<String>,
     username: Option<String>,
     post_type: Option<String>,
+    replacement_domain: Option<String>,
+    /// The guild's format for the output, see `Platform::label`.
+    label: Option<String>,
     spoiler: bool,
 }
 
 impl UrlProcessor {
+    /// Detects the platform of the url. `spoiler` should be worked out against the
+    /// full message (see `utils::markdown`), since the input may be a lone url.
     pub fn try_new(input: &str, spoiler: bool) -> Option<Self> {
-        let platform = Platform::try_detect(input)?;
-        Some(Self {
+        let platform = platforms::load().detect(input)?;
+        Some(Self::new(platform, input, spoiler))
+    }
+
+    fn new(platform: &'static Platform, input: &str, spoiler: bool) -> Self {
+        Self {
             platform,
             user_input: input.to_string(),
             clean_url: None,
             username: None,
             post_type: None,
+            replacement_domain: None,
+            label: None,
             spoiler,
-        })
+        }
+    }
+
+    /// Applies the guild's preferences, if the url was sent in a guild.
+    pub fn with_config(mut self, config: Option<&ServerConfig>) -> Self {
+        self.replacement_domain = config
+            .and_then(|config| config.replacement_domains.get(self.platform.id()))
+            .cloned();
+        self.label = config.and_then(|config| config.reply_template.clone());
+        self
+    }
+
+    /// Whether the platform is turned on, always the case outside guilds.
+    pub fn is_enabled(&self, config: Option<&ServerConfig>) -> bool {
+        config.is_none_or(|config| config.is_platform_enabled(self.platform.id()))
     }
 
     pub async fn capture_url(mut self) -> Option<Self> {
-        let regex = &INDIVIDUAL_REGEXES[self.platform as usize];
-        let captures = regex.captures(&self.user_input)?;
+        self.resolve_share_link().await;
 
-        // Checks if content has a spoiler if spoiler hasn't already been manually set
-        if let Some(capture) = captures.get(0) {
-            self.spoiler = self.spoiler || self.is_spoiler(capture.start(), capture.end());
-        }
+        let captures = self.platform.captures(&self.user_input)?;
 
-        match self.platform {
-            Platform::Instagram => {
-                tracing::debug!("Successfully matched the platform: Instagram");
-                let post_type = captures.name("type")?.as_str();
-                let data = captures.name("data")?.as_str();
-                let clean_url = format!(
-                    "https://www.{}/{}{}",
-                    self.platform.replacement_domain(),
-                    post_type,
-                    data
-                );
-
-                self.post_type = Some(post_type.to_string());
-                self.clean_url = Some(clean_url);
-            }
-            Platform::Reddit => {
-                tracing::debug!("Successfully matched the platform: Reddit");
-                let subdomain = captures
-                    .name("subdomain")
-                    .map(|m| m.as_str())
-                    .unwrap_or("www.");
-                let subreddit = captures.name("subreddit")?.as_str();
-                let data = captures.name("data")?.as_str();
-
-                let clean_url = format!(
-                    "https://{}{}/{}{}",
-                    subdomain,
-                    self.platform.replacement_domain(),
-                    subreddit,
-                    data
-                );
-
-                self.username = Some(subreddit.into());
-                self.clean_url = Some(clean_url);
-            }
-            Platform::TikTok => {
-                tracing::debug!("Successfully matched the platform: TikTok");
-                let subdomain = captures.name("subdomain").map(|m| m.as_str()).unwrap_or("");
-                let data = captures.name("data")?.as_str();
-                let clean_url = format!(
-                    "https://{}{}{}",
-                    subdomain,
-                    self.platform.replacement_domain(),
-                    data
-                );
-
-                let username = match self.get_original_url() {
-                    Some(original_url) => Self::get_author(original_url.as_str(), self.platform)
-                        .await
-                        .ok()
-                        .flatten(),
-                    None => None,
-                };
+        tracing::debug!(
+            "Successfully matched the platform: {}",
+            self.platform.display_name()
+        );
 
-                self.username = username;
-                self.clean_url = Some(clean_url);
-            }
-            Platform::Twitter => {
-                tracing::debug!("Successfully matched the platform: Twitter");
-                let username = captures.name("username")?.as_str();
-                let data = captures.name("data")?.as_str();
-                let clean_url = format!(
-                    "https://{}/{}{}",
-                    self.platform.replacement_domain(),
-                    username,
-                    data
-                );
-
-                self.clean_url = Some(clean_url);
-                self.username = Some(username.to_string());
-            } // Platform::Twitch => {
-              //     tracing::debug!("Successfully matched the platform: Twitch");
-              //     let username = captures.name("username").map(|m| m.as_str().to_string());
-              //     let data = captures.name("data")?.as_str();
-
-              //     let clean_url = if let Some(ref user) = username {
-              //         // Format: twitch.tv/username/clip/data
-              //         format!(
-              //             "https://{}/{}/clip/{}",
-              //             self.platform.replacement_domain(),
-              //             user,
-              //             data
-              //         )
-              //     } else {
-              //         // Format: clips.twitch.tv/clipid
-              //         format!(
-              //             "https://{}/clip/{}",
-              //             self.platform.replacement_domain(),
-              //             data
-              //         )
-              //     };
-
-              //     let username = match username {
-              //         Some(u) => Some(u),
-              //         None => Self::get_author(&clean_url, self.platform)
-              //             .await
-              //             .ok()
-              //             .flatten(),
-              //     };
-
-              //     self.clean_url = Some(clean_url);
-              //     self.username = username;
-              // }
+        let domain = health::pick_domain(self.platform, self.replacement_domain.as_deref());
+        let mut clean_url = self.platform.rewrite(&captures, domain);
+        if let Some(capture) = captures.get(0) {
+            clean_url = self
+                .platform
+                .with_query(clean_url, &self.user_input, capture.end());
         }
+        let post_type = self.platform.kind(&captures);
+        let mut username = self.platform.author(&captures);
+
+        if username.is_none()
+            && let Some(resolver) = self.platform.resolver()
+            && let Some(original_url) = self.get_original_url()
+        {
+            username = Self::get_author(&original_url, resolver)
+                .await
+                .ok()
+                .flatten();
+        }
+
+        self.post_type = Some(post_type);
+        self.username = username;
+        self.clean_url = Some(clean_url);
 
         Some(self)
     }
@@ -246,44 +102,12 @@ impl UrlProcessor {
         tracing::debug!("Attempting to format the final output.");
         let clean_url = self.clean_url?;
 
-        let formatted_string = match self.platform {
-            Platform::Instagram => {
-                let post_type_display = self
-                    .post_type
-                    .as_ref()
-                    .map(|pt| match pt.to_lowercase().as_str() {
-                        "reels" | "reel" => "Reel",
-                        "p" => "Post",
-                        _ => "Post",
-                    })
-                    .unwrap_or("Post");
-
-                format!(
-                    "[{} via {}]({})",
-                    post_type_display,
-                    self.platform.display_name(),
-                    clean_url
-                )
-            }
-            Platform::Reddit => match self.username {
-                Some(username) => format!(
-                    "[{} via {}]({})",
-                    username,
-                    self.platform.display_name(),
-                    clean_url
-                ),
-                None => format!("[Post via {}]({})", self.platform.display_name(), clean_url),
-            },
-            Platform::TikTok | Platform::Twitter => match self.username {
-                Some(username) => format!(
-                    "[@{} via {}]({})",
-                    username,
-                    self.platform.display_name(),
-                    clean_url
-                ),
-                None => format!("[Post via {}]({})", self.platform.display_name(), clean_url),
-            },
-        };
+        let formatted_string = self.platform.label(
+            self.label.as_deref(),
+            self.username.as_deref(),
+            self.post_type.as_deref().unwrap_or("Post"),
+            &clean_url,
+        );
 
         if self.spoiler {
             Some(format!("|| {} ||", formatted_string))
@@ -292,36 +116,72 @@ impl UrlProcessor {
         }
     }
 
+    /// Returns the fixed url, once captured.
+    pub fn get_clean_url(&self) -> Option<&str> {
+        self.clean_url.as_deref()
+    }
+
+    /// Returns the url as written by the user, minus any tracking parameters.
     pub fn get_original_url(&self) -> Option<String> {
         tracing::debug!("Getting original url");
-        let regex = &INDIVIDUAL_REGEXES[self.platform as usize];
-        regex.find(&self.user_input).map(|m| m.as_str().to_string())
+        self.platform.find(&self.user_input).map(|m| {
+            self.platform
+                .with_query(m.as_str().to_string(), &self.user_input, m.end())
+        })
+    }
+
+    /// Replaces a share link in the input with the canonical url it redirects to,
+    /// so both the fixed link and the "Open Link" button point at the post itself.
+    async fn resolve_share_link(&mut self) {
+        let Some(url) = self.get_original_url() else {
+            return;
+        };
+        if !self.platform.is_share_link(&url) {
+            return;
+        }
+
+        match Self::follow_redirect(&url).await {
+            Ok(Some(location)) if self.platform.find(&location).is_some() => {
+                tracing::debug!("Resolved share link to its canonical url");
+                self.user_input = location;
+            }
+            Ok(_) => tracing::debug!("Share link did not redirect to a supported url"),
+            Err(e) => tracing::debug!("Failed to resolve share link: {:?}", e),
+        }
     }
 
-    /// Checks if the url in the input is part of a spoiler.
-    fn is_spoiler(&self, match_start: usize, match_end: usize) -> bool {
-        let pre_url = &self.user_input[..match_start];
-        let post_url = &self.user_input[match_end..];
+    /// Returns the absolute url the `location` header of the response points to.
+    async fn follow_redirect(url: &str) -> anyhow::Result<Option<String>> {
+        http::REDIRECTS
+            .get_or_fetch(url.to_string(), async {
+                let response = http::client().get(url).send().await?;
+                let Some(location) = response.headers().get(reqwest::header::LOCATION) else {
+                    return Ok(None);
+                };
 
-        let pipes_before = pre_url.matches("||").count();
-        let pipes_after = post_url.contains("||");
+                let location = response.url().join(location.to_str()?)?;
+                Ok(Some(location.to_string()))
+            })
+            .await
+    }
 
-        // Logic:
-        // 1. Before url, odd number of pipes
-        // 2. After url, at least one pipe
-        pipes_before % 2 == 1 && pipes_after
+    /// Retrieve's the author name, caching the result per url.
+    async fn get_author(url: &str, resolver: AuthorResolver) -> anyhow::Result<Option<String>> {
+        http::AUTHORS
+            .get_or_fetch(
+                format!("{:?} {}", resolver, url),
+                Self::fetch_author(url, resolver),
+            )
+            .await
     }
 
-    /// Retrieve's the author name by attempting to curl the url and parse the output.
-    async fn get_author(url: &str, platform: Platform) -> anyhow::Result<Option<String>> {
-        tracing::debug!("Attempting to get author, building reqwest client");
-        let client = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 (compatible; Discordbot/2.0; +https://discordapp.com/)")
-            .redirect(reqwest::redirect::Policy::none())
-            .build()?;
+    /// Attempts to curl the url and parse the author from the output.
+    async fn fetch_author(url: &str, resolver: AuthorResolver) -> anyhow::Result<Option<String>> {
+        tracing::debug!("Attempting to get author");
+        let client = http::client();
 
-        let author = match platform {
-            Platform::TikTok => {
+        let author = match resolver {
+            AuthorResolver::TiktokRedirect => {
                 let response = client.get(url).send().await?;
                 let Some(location) = response.headers().get("location") else {
                     return Ok(None);
@@ -341,32 +201,187 @@ impl UrlProcessor {
 
                 Some(location[start + 2..start + 2 + end].to_string())
             }
-            // Twitter doesn't ever get ran, added for future use.
-            // Platform::Twitch | Platform::Twitter => {
-            //     let html = client.get(url).send().await?.text().await?;
-            //     let document = scraper::Html::parse_document(&html);
-            //     let selector_property = match platform {
-            //         Platform::Twitch => "og:title",
-            //         Platform::Twitter => "twitter:creator",
-            //         _ => unreachable!(),
-            //     };
-
-            //     let selector = Selector::parse(&format!("meta[property='{}']", selector_property))
-            //         .expect("valid CSS selector");
-
-            //     document
-            //         .select(&selector)
-            //         .next()
-            //         .and_then(|el| el.value().attr("content"))
-            //         .map(|content| match platform {
-            //             Platform::Twitch => {
-            //                 content.split(" - ").next().unwrap_or(content).to_string()
-            //             }
-            //             _ => content.to_string(),
-            //         })
-            // }
-            _ => None,
+            AuthorResolver::BlueskyDid => {
+                let Some(did) = url.split('/').find(|segment| segment.starts_with("did:")) else {
+                    return Ok(None);
+                };
+
+                let profile = client
+                    .get(format!("{}?actor={}", BLUESKY_PROFILE_API, did))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<BlueskyProfile>()
+                    .await?;
+
+                Some(profile.handle)
+            }
+            AuthorResolver::OgTitle => {
+                // Read from the platform itself, since fixers differ in what they put there.
+                let html = client.get(url).send().await?.text().await?;
+                let document = scraper::Html::parse_document(&html);
+                let selector =
+                    Selector::parse("meta[property='og:title']").expect("valid CSS selector");
+
+                document
+                    .select(&selector)
+                    .next()
+                    .and_then(|el| el.value().attr("content"))
+                    .and_then(|content| content.split(" - ").next())
+                    .map(|author| author.trim().to_string())
+                    .filter(|author| !author.is_empty())
+            }
         };
         Ok(author)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::*;
+    use crate::sanitize::PlatformRegistry;
+    use crate::utils::testing::{self, StubRequests};
+
+    const CLIP_PAGE: &str = r#"<html><head>
+        <meta property="og:title" content="streamer - Clip title" />
+    </head></html>"#;
+
+    fn spawn_clip_server() -> (SocketAddr, StubRequests) {
+        testing::spawn_stub_server(|_| testing::http_response("200 OK", "text/html", CLIP_PAGE))
+    }
+
+    fn paths(requests: &StubRequests) -> Vec<String> {
+        let requests = requests.lock().unwrap();
+        requests
+            .iter()
+            .map(|request| request.path.clone())
+            .collect()
+    }
+
+    /// Built-in registry with the Twitch fixer pointed at the stub server, which
+    /// also stands in for `clips.twitch.tv`.
+    fn stub_registry(addr: SocketAddr) -> &'static PlatformRegistry {
+        let definitions = PlatformRegistry::builtin_definitions()
+            .unwrap()
+            .into_iter()
+            .map(|mut definition| {
+                if definition.id.starts_with("twitch") {
+                    definition.domain = addr.to_string();
+                    definition.rewrite = definition.rewrite.replace("https://", "http://");
+                }
+                if definition.id == "twitch_clips" {
+                    definition.hosts.push(addr.ip().to_string());
+                    definition.pattern = definition
+                        .pattern
+                        .replace(r"clips\.twitch\.tv", &regex::escape(&addr.to_string()));
+                }
+                definition
+            })
+            .collect();
+
+        Box::leak(Box::new(
+            PlatformRegistry::from_definitions(definitions).unwrap(),
+        ))
+    }
+
+    async fn sanitize(registry: &'static PlatformRegistry, input: &str) -> String {
+        let platform = registry.detect(input).unwrap();
+        UrlProcessor::new(platform, input, false)
+            .capture_url()
+            .await
+            .and_then(|url| url.format_output())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_twitch_clip_with_username() {
+        let (addr, requests) = spawn_clip_server();
+        let registry = stub_registry(addr);
+
+        let output = sanitize(
+            registry,
+            "https://www.twitch.tv/streamer/clip/FunnyClip-abc?tt_medium=x",
+        )
+        .await;
+
+        // The author is part of the url, so nothing is looked up.
+        let fixed_url = format!("http://{}/streamer/clip/FunnyClip-abc", addr);
+        assert_eq!(output, format!("[@streamer via Twitch]({})", fixed_url));
+        assert!(requests.lock().unwrap().is_empty());
+
+        // The fixed url points at the clip on the fixer.
+        let page = reqwest::get(&fixed_url)
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(page, CLIP_PAGE);
+        assert_eq!(paths(&requests), ["/streamer/clip/FunnyClip-abc"]);
+    }
+
+    #[tokio::test]
+    async fn test_spoiler_wraps_output() {
+        let (addr, _) = spawn_clip_server();
+        let registry = stub_registry(addr);
+        let input = "https://www.twitch.tv/streamer/clip/FunnyClip-abc";
+
+        let output = UrlProcessor::new(registry.detect(input).unwrap(), input, true)
+            .capture_url()
+            .await
+            .and_then(|url| url.format_output())
+            .unwrap();
+
+        assert_eq!(
+            output,
+            format!(
+                "|| [@streamer via Twitch](http://{}/streamer/clip/FunnyClip-abc) ||",
+                addr
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_twitch_clips_subdomain_resolves_author() {
+        let (addr, requests) = spawn_clip_server();
+        let registry = stub_registry(addr);
+
+        // Sharing the same clip again is served from the author cache.
+        for _ in 0..2 {
+            let output = sanitize(
+                registry,
+                &format!("http://{}/FunnyClip-abc?tt_medium=x", addr),
+            )
+            .await;
+
+            assert_eq!(
+                output,
+                format!("[@streamer via Twitch](http://{}/clip/FunnyClip-abc)", addr)
+            );
+        }
+        // The author is read from the clip's own page, not the fixer's.
+        assert_eq!(paths(&requests), ["/FunnyClip-abc"]);
+    }
+
+    #[tokio::test]
+    async fn test_follow_redirect() {
+        let (addr, _) = testing::spawn_stub_server(|_| {
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: /r/rust/comments/abc/title/?share_id=x\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string()
+        });
+
+        let location = UrlProcessor::follow_redirect(&format!("http://{}/r/rust/s/xyz", addr))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            location,
+            Some(format!(
+                "http://{}/r/rust/comments/abc/title/?share_id=x",
+                addr
+            ))
+        );
+    }
+}
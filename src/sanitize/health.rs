@@ -0,0 +1,225 @@
+//! Periodically probes every fixer domain so a dead mirror can be skipped in favour
+//! of the next domain in the platform's fallback order.
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use reqwest::StatusCode;
+use tokio::task::JoinSet;
+
+use crate::sanitize::http;
+use crate::sanitize::platforms::{self, Platform};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(300);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The health of every fixer domain, kept up to date by `health_worker`.
+static HEALTH: LazyLock<DomainHealth> = LazyLock::new(DomainHealth::default);
+
+/// Picks the domain to use for a platform, see `DomainHealth::pick_domain`.
+pub fn pick_domain<'a>(platform: &'a Platform, preferred: Option<&'a str>) -> &'a str {
+    HEALTH.pick_domain(platform, preferred)
+}
+
+/// Last known health of each domain. Domains that were never probed count as healthy.
+#[derive(Debug, Default)]
+pub struct DomainHealth {
+    domains: DashMap<String, bool>,
+}
+
+impl DomainHealth {
+    pub fn is_healthy(&self, domain: &str) -> bool {
+        self.domains.get(domain).is_none_or(|healthy| *healthy)
+    }
+
+    /// Picks the first healthy domain in fallback order. If every domain is down,
+    /// the preferred one is used anyway.
+    pub fn pick_domain<'a>(&self, platform: &'a Platform, preferred: Option<&'a str>) -> &'a str {
+        platform
+            .fallback_domains(preferred)
+            .find(|domain| self.is_healthy(domain))
+            .unwrap_or_else(|| platform.replacement_domain(preferred))
+    }
+
+    /// Probes the domain at `url` and records whether it's healthy.
+    pub async fn check(&self, client: &reqwest::Client, domain: &str, url: &str) {
+        let healthy = probe(client, url).await;
+        self.record(domain.to_string(), healthy);
+    }
+
+    fn record(&self, domain: String, healthy: bool) {
+        let previous = self.domains.insert(domain.clone(), healthy);
+
+        match (previous, healthy) {
+            (Some(true) | None, false) => {
+                tracing::warn!("Fixer domain {} is unhealthy, falling back", domain)
+            }
+            (Some(false), true) => tracing::info!("Fixer domain {} has recovered", domain),
+            _ => (),
+        }
+    }
+}
+
+/// Probes all domains every `HEALTH_CHECK_INTERVAL` seconds (default 300).
+pub async fn health_worker() {
+    let interval = std::env::var("HEALTH_CHECK_INTERVAL")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_INTERVAL);
+
+    let domains: HashSet<String> = platforms::load()
+        .iter()
+        .flat_map(|platform| platform.domains().map(str::to_string))
+        .collect();
+
+    tracing::debug!(
+        "Health checking {} fixer domains every {:?}",
+        domains.len(),
+        interval
+    );
+
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+
+        let mut probes = JoinSet::new();
+        for domain in &domains {
+            let domain = domain.clone();
+            probes.spawn(async move {
+                let url = format!("https://{}/", domain);
+                HEALTH.check(http::client(), &domain, &url).await;
+            });
+        }
+
+        while let Some(result) = probes.join_next().await {
+            if let Err(e) = result {
+                tracing::warn!("Health check probe panicked: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Sends a HEAD request (or a GET, for servers that don't allow HEAD) to the url.
+/// Only successes and redirects count as healthy, since a fixer answering every
+/// request with an error is as good as down.
+async fn probe(client: &reqwest::Client, url: &str) -> bool {
+    let mut response = client.head(url).timeout(PROBE_TIMEOUT).send().await;
+    if response
+        .as_ref()
+        .is_ok_and(|response| response.status() == StatusCode::METHOD_NOT_ALLOWED)
+    {
+        response = client.get(url).timeout(PROBE_TIMEOUT).send().await;
+    }
+
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            if !status.is_success() && !status.is_redirection() {
+                tracing::debug!(%url, %status, "Health check got an error status");
+            }
+            status.is_success() || status.is_redirection()
+        }
+        Err(e) => {
+            tracing::debug!(%url, "Health check failed: {:?}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::sanitize::PlatformRegistry;
+
+    /// Answers every request with the current status, recording the request lines.
+    fn spawn_stub_server(
+        status: Arc<Mutex<&'static str>>,
+    ) -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 1024];
+                let len = stream.read(&mut buf).unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..len]);
+                let line = request.lines().next().unwrap_or_default();
+                recorded.lock().unwrap().push(line.to_string());
+
+                let status = *status.lock().unwrap();
+                // HEAD is only allowed when healthy, like some fixers.
+                let status = if line.starts_with("HEAD") && status != "200 OK" {
+                    "405 Method Not Allowed"
+                } else {
+                    status
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        (addr, requests)
+    }
+
+    #[tokio::test]
+    async fn test_pick_domain_skips_unhealthy_domain_until_it_recovers() {
+        let status = Arc::new(Mutex::new("404 Not Found"));
+        let (addr, requests) = spawn_stub_server(status.clone());
+        let url = format!("http://{}/", addr);
+
+        let registry =
+            PlatformRegistry::from_definitions(PlatformRegistry::builtin_definitions().unwrap())
+                .unwrap();
+        let twitter = registry.get("twitter").unwrap();
+        let client = reqwest::Client::new();
+        let health = DomainHealth::default();
+
+        assert_eq!(health.pick_domain(twitter, None), "fxtwitter.com");
+
+        // Not found for everything is no better than being down.
+        health.check(&client, "fxtwitter.com", &url).await;
+        assert_eq!(health.pick_domain(twitter, None), "fixupx.com");
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["HEAD / HTTP/1.1", "GET / HTTP/1.1"]
+        );
+
+        *status.lock().unwrap() = "200 OK";
+        health.check(&client, "fxtwitter.com", &url).await;
+        assert_eq!(health.pick_domain(twitter, None), "fxtwitter.com");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_domain_is_unhealthy() {
+        // Nothing listens on the port once the listener is dropped.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let health = DomainHealth::default();
+
+        health
+            .check(
+                &reqwest::Client::new(),
+                "fxtwitter.com",
+                &format!("http://{}/", addr),
+            )
+            .await;
+
+        assert!(!health.is_healthy("fxtwitter.com"));
+        assert!(health.is_healthy("fixupx.com"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Shared HTTP client for every outgoing lookup, along with caches of resolved
+//! redirects and authors so repeated shares of a link don't hit the network.
+
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use lru::LruCache;
+
+const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (compatible; Discordbot/2.0; +https://discordapp.com/)";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(8);
+const CACHE_CAPACITY: usize = 1000;
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Pooled client that never follows redirects, since the `location` header is
+/// usually what we're after. The user agent can be set with `HTTP_USER_AGENT`.
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    let user_agent =
+        std::env::var("HTTP_USER_AGENT").unwrap_or_else(|_| DEFAULT_USER_AGENT.to_string());
+
+    reqwest::Client::builder()
+        .user_agent(user_agent)
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Valid HTTP client")
+});
+
+/// Resolved redirect targets, keyed by the short url.
+pub static REDIRECTS: LazyLock<ResolutionCache> =
+    LazyLock::new(|| ResolutionCache::new(CACHE_CAPACITY, CACHE_TTL));
+/// Resolved authors, keyed by the resolver and the original url.
+pub static AUTHORS: LazyLock<ResolutionCache> =
+    LazyLock::new(|| ResolutionCache::new(CACHE_CAPACITY, CACHE_TTL));
+
+pub fn client() -> &'static reqwest::Client {
+    &CLIENT
+}
+
+/// LRU cache whose entries also expire after a fixed time to live.
+#[derive(Debug)]
+pub struct ResolutionCache {
+    entries: Mutex<LruCache<String, (Instant, Option<String>)>>,
+    ttl: Duration,
+}
+
+impl ResolutionCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).expect("Capacity must be > 0");
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    /// Returns the cached value for the key, else runs the lookup and caches its
+    /// result. Negative results (`None`) are cached too, errors are not.
+    pub async fn get_or_fetch<F>(&self, key: String, fetch: F) -> anyhow::Result<Option<String>>
+    where
+        F: Future<Output = anyhow::Result<Option<String>>>,
+    {
+        if let Some(value) = self.get(&key) {
+            tracing::debug!("Found resolution in cache");
+            return Ok(value);
+        }
+
+        let value = fetch.await?;
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.put(key, (Instant::now(), value.clone()));
+        }
+
+        Ok(value)
+    }
+
+    fn get(&self, key: &str) -> Option<Option<String>> {
+        let mut entries = self.entries.lock().ok()?;
+        let (inserted, value) = entries.get(key)?;
+
+        if inserted.elapsed() < self.ttl {
+            return Some(value.clone());
+        }
+
+        entries.pop(key);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    async fn fetch(calls: &AtomicUsize) -> anyhow::Result<Option<String>> {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Ok(Some("author".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_skip_fetch() {
+        let cache = ResolutionCache::new(10, Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+
+        for _ in 0..3 {
+            let value = cache
+                .get_or_fetch("https://vm.tiktok.com/abc".to_string(), fetch(&calls))
+                .await
+                .unwrap();
+            assert_eq!(value.as_deref(), Some("author"));
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_expires_and_skips_errors() {
+        let cache = ResolutionCache::new(10, Duration::ZERO);
+        let calls = AtomicUsize::new(0);
+
+        cache
+            .get_or_fetch("a".to_string(), fetch(&calls))
+            .await
+            .unwrap();
+        cache
+            .get_or_fetch("a".to_string(), fetch(&calls))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let cache = ResolutionCache::new(10, Duration::from_secs(60));
+        let failed = cache
+            .get_or_fetch("b".to_string(), async { anyhow::bail!("timed out") })
+            .await;
+        assert!(failed.is_err());
+        assert!(cache.get("b").is_none());
+    }
+}
@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use anyhow::Context;
 use twilight_http::Client;
 use twilight_http::request::channel::reaction::RequestReactionType;
 use twilight_model::channel::Message;
@@ -5,23 +8,303 @@ use twilight_model::channel::message::component::{ActionRow, Button, ButtonStyle
 use twilight_model::channel::message::{
     AllowedMentions, Component, EmojiReactionType, MessageFlags, MessageType,
 };
+use twilight_model::id::Id;
 
-use crate::db::{ResponseMap, ServerConfig};
+use crate::db::{ResponseMap, ResponseStore, ServerConfig};
+use crate::discord::WebhookCache;
 use crate::discord::models::{DeletePermission, SanitizerMode};
-use crate::sanitize::UrlProcessor;
+use crate::sanitize::{UrlProcessor, rewrite_message};
 use crate::utils;
 
+/// The longest message content Discord accepts.
+const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// The custom id of the delete button, suffixed with `:{author_id}` on reposts
+/// since those don't reference the author's message.
+const DELETE_BUTTON_ID: &str = "delete";
+
+/// The bot's reply to a message, before it's sent.
+struct Reply {
+    content: String,
+    components: Vec<Component>,
+}
+
 /// Converts the URL in a message if there is a valid URL.
 pub async fn process_message(
     message: &Message,
     client: &Client,
     server_config: Option<ServerConfig>,
+    responses: &dyn ResponseStore,
+) -> anyhow::Result<()> {
+    if server_config
+        .as_ref()
+        .is_some_and(|config| config.sanitizer_mode == SanitizerMode::Disabled)
+    {
+        tracing::debug!("Sanitizer is disabled, not replying");
+        return Ok(());
+    }
+
+    let Some(reply) = build_reply(message, server_config.as_ref(), DELETE_BUTTON_ID).await? else {
+        return Ok(());
+    };
+
+    let bot_response = client
+        .create_message(message.channel_id)
+        .content(&reply.content)
+        .components(&reply.components)
+        .reply(message.id)
+        .allowed_mentions(Some(&AllowedMentions {
+            replied_user: false,
+            ..Default::default()
+        }))
+        .await?
+        .model()
+        .await?;
+
+    // Saves the response in the response map.
+    let response_map = ResponseMap::new(message, bot_response.id);
+    if let Err(e) = responses.save_response(&response_map).await {
+        tracing::warn!("Failed to save response_map due to: {:?}", e);
+    }
+
+    // Early exits if message is not in a server
+    let Some(server_config) = server_config else {
+        return Ok(());
+    };
+
+    // Removes all Sanitized emoji reactions after responding.
+    if server_config.sanitizer_mode == SanitizerMode::ManualEmote
+        || server_config.sanitizer_mode == SanitizerMode::ManualBoth
+    {
+        tracing::debug!("Removing all Sanitized emoji reactions.");
+        client
+            .delete_all_reaction(
+                message.channel_id,
+                message.id,
+                &RequestReactionType::Custom {
+                    id: crate::EMOJI_ID.get().unwrap().to_owned(),
+                    name: Some("Sanitized"),
+                },
+            )
+            .await?;
+    }
+
+    if server_config.hide_original_embed {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        if responses.find_match(message.id).await?.is_some()
+            && let Err(e) = client
+                .update_message(message.channel_id, message.id)
+                .flags(MessageFlags::SUPPRESS_EMBEDS)
+                .await
+        {
+            tracing::debug!("Failed to suppress embed (likely already deleted): {:?}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the message with a copy whose links are fixed, sent through a
+/// webhook under the author's name and avatar. Falls back to replying when the
+/// message can't be copied as is, or the webhook can't be used.
+pub async fn repost_message(
+    message: &Message,
+    client: &Client,
+    server_config: ServerConfig,
+    responses: &dyn ResponseStore,
+    webhooks: &WebhookCache,
+) -> anyhow::Result<()> {
+    if !can_repost(message) {
+        tracing::debug!("Message can't be reposted, replying instead");
+        return process_message(message, client, Some(server_config), responses).await;
+    }
+
+    let delete_button_id = format!("{}:{}", DELETE_BUTTON_ID, message.author.id);
+    let Some(reply) = build_reply(message, Some(&server_config), &delete_button_id).await? else {
+        return Ok(());
+    };
+
+    let content = rewrite_message(&message.content, &server_config).await?;
+    if content.chars().count() > MAX_CONTENT_LENGTH {
+        tracing::debug!("Reposted message would be too long, replying instead");
+        return process_message(message, client, Some(server_config), responses).await;
+    }
+
+    let repost = match send_repost(message, client, &content, &reply.components, webhooks).await {
+        Ok(repost) => repost,
+        Err(e) => {
+            tracing::warn!("Failed to repost message, replying instead: {:?}", e);
+            webhooks.forget(message.channel_id);
+            return process_message(message, client, Some(server_config), responses).await;
+        }
+    };
+
+    // Saved before deleting the original, so its delete event is known to be ours.
+    if let Err(e) = responses
+        .save_response(&ResponseMap::repost(message, repost.id))
+        .await
+    {
+        tracing::warn!("Failed to save response_map due to: {:?}", e);
+    }
+
+    client
+        .delete_message(message.channel_id, message.id)
+        .await
+        .context("Failed to delete reposted message")?;
+
+    Ok(())
+}
+
+/// Whether the message is plain text, since attachments, stickers, polls and
+/// replies can't be carried over by a webhook.
+fn can_repost(message: &Message) -> bool {
+    message.kind == MessageType::Regular
+        && message.attachments.is_empty()
+        && message.sticker_items.is_empty()
+        && message.poll.is_none()
+}
+
+/// Sends the content through the channel's webhook, looking like the author.
+async fn send_repost(
+    message: &Message,
+    client: &Client,
+    content: &str,
+    components: &[Component],
+    webhooks: &WebhookCache,
+) -> anyhow::Result<Message> {
+    let webhook = webhooks.get_or_create(client, message.channel_id).await?;
+    let username = display_name(message);
+    let avatar_url = avatar_url(message);
+    // Mentions already pinged with the original message.
+    let allowed_mentions = AllowedMentions::default();
+
+    let mut request = client
+        .execute_webhook(webhook.id, &webhook.token)
+        .content(content)
+        .username(username)
+        .avatar_url(&avatar_url)
+        .components(components)
+        .allowed_mentions(Some(&allowed_mentions));
+    if let Some(thread_id) = webhook.thread_id {
+        request = request.thread_id(thread_id);
+    }
+
+    Ok(request.wait().await?.model().await?)
+}
+
+/// The name the author is shown with in the guild.
+fn display_name(message: &Message) -> &str {
+    message
+        .member
+        .as_ref()
+        .and_then(|member| member.nick.as_deref())
+        .or(message.author.global_name.as_deref())
+        .unwrap_or(&message.author.name)
+}
+
+/// The avatar the author is shown with in the guild.
+fn avatar_url(message: &Message) -> String {
+    let user_id = message.author.id;
+    let guild_avatar = message.member.as_ref().and_then(|member| member.avatar);
+
+    match (message.guild_id, guild_avatar, message.author.avatar) {
+        (Some(guild_id), Some(hash), _) => format!(
+            "https://cdn.discordapp.com/guilds/{}/users/{}/avatars/{}.png",
+            guild_id, user_id, hash
+        ),
+        (_, _, Some(hash)) => format!(
+            "https://cdn.discordapp.com/avatars/{}/{}.png",
+            user_id, hash
+        ),
+        _ => format!(
+            "https://cdn.discordapp.com/embed/avatars/{}.png",
+            (user_id.get() >> 22) % 6
+        ),
+    }
+}
+
+/// Brings an existing reply in line with the edited message. The reply is
+/// edited in place, or deleted if the message no longer has supported links.
+pub async fn update_reply(
+    message: &Message,
+    client: &Client,
+    server_config: Option<ServerConfig>,
+    responses: &dyn ResponseStore,
+    response_map: ResponseMap,
 ) -> anyhow::Result<()> {
+    let Some(reply) = build_reply(message, server_config.as_ref(), DELETE_BUTTON_ID).await? else {
+        tracing::debug!(message_id = %message.id, "No supported links left after edit");
+        return delete_reply(client, responses, response_map).await;
+    };
+
+    client
+        .update_message(
+            Id::new(response_map.channel_id),
+            Id::new(response_map.bot_message_id),
+        )
+        .content(Some(&reply.content))
+        .components(Some(&reply.components))
+        .await
+        .context("Failed to update bot response")?;
+
+    tracing::debug!(message_id = %message.id, "Updated bot response after edit");
+
+    // Suppressing the embed fires another update, so only do it when needed.
+    if server_config.is_some_and(|config| config.hide_original_embed)
+        && !message
+            .flags
+            .is_some_and(|flags| flags.contains(MessageFlags::SUPPRESS_EMBEDS))
+        && let Err(e) = client
+            .update_message(message.channel_id, message.id)
+            .flags(MessageFlags::SUPPRESS_EMBEDS)
+            .await
+    {
+        tracing::debug!("Failed to suppress embed (likely already deleted): {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// Deletes the bot's reply along with its response map entry.
+pub async fn delete_reply(
+    client: &Client,
+    responses: &dyn ResponseStore,
+    response_map: ResponseMap,
+) -> anyhow::Result<()> {
+    if let Err(e) = client
+        .delete_message(
+            Id::new(response_map.channel_id),
+            Id::new(response_map.bot_message_id),
+        )
+        .await
+    {
+        tracing::debug!(error = ?e, "Bot message already gone (likely race)");
+    }
+
+    responses
+        .delete_response(response_map.user_message_id)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to delete response map entry for message {}",
+                response_map.user_message_id
+            )
+        })
+}
+
+/// Builds the reply for every supported link in the message, falling back to the
+/// referenced message when the bot is summoned with a reply.
+async fn build_reply(
+    message: &Message,
+    server_config: Option<&ServerConfig>,
+    delete_button_id: &str,
+) -> anyhow::Result<Option<Reply>> {
     let mut target_message = message;
     let mut all_links = utils::get_links(target_message);
 
     if all_links.is_empty() {
-        let fallback = if let Some(config) = server_config.as_ref()
+        let fallback = if let Some(config) = server_config
             && (config.sanitizer_mode == SanitizerMode::ManualMention
                 || config.sanitizer_mode == SanitizerMode::ManualBoth)
             && message.kind == MessageType::Reply
@@ -40,36 +323,56 @@ pub async fn process_message(
 
     // Exits early if no links are found
     if all_links.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
     let mut combined_outputs = Vec::new();
+    let mut content_length = 0;
     let mut processed_urls = Vec::new();
 
     for link in &all_links {
-        let Some(url) = UrlProcessor::try_new(link, false) else {
+        let Some(url) = UrlProcessor::try_new(link.url, link.spoiler) else {
             continue;
         };
-
-        let Some(original_url) = url.get_original_url() else {
-            tracing::error!("Original URL was not found.");
+        if !url.is_enabled(server_config) {
+            tracing::debug!("Skipping link of a disabled platform");
             continue;
-        };
+        }
+        tracing::debug!(
+            masked = link.masked,
+            spoiler = link.spoiler,
+            "Processing supported link"
+        );
+        let url = url.with_config(server_config);
 
         let Some(captures) = url.capture_url().await else {
             return Err(anyhow::anyhow!("Failed to process URL"));
         };
 
+        // Retrieved after capturing, since share links resolve to a canonical url.
+        let Some(original_url) = captures.get_original_url() else {
+            tracing::error!("Original URL was not found.");
+            continue;
+        };
+
         let Some(output) = captures.format_output() else {
             return Err(anyhow::anyhow!("Failed to process URL"));
         };
 
+        // Links that don't fit in the reply (one per line) are left out.
+        let output_length = output.chars().count() + usize::from(!combined_outputs.is_empty());
+        if content_length + output_length > MAX_CONTENT_LENGTH {
+            tracing::debug!("Skipping link that doesn't fit in the reply");
+            continue;
+        }
+        content_length += output_length;
+
         combined_outputs.push(output);
         processed_urls.push(original_url);
     }
 
     if combined_outputs.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
     let mut all_buttons = Vec::new();
@@ -106,7 +409,7 @@ pub async fn process_message(
         };
         all_buttons.push(Component::Button(Button {
             id: None,
-            custom_id: Some("delete".to_owned()),
+            custom_id: Some(delete_button_id.to_owned()),
             disabled: false,
             emoji: Some(delete_emoji),
             label: Some("Delete".to_owned()),
@@ -116,8 +419,6 @@ pub async fn process_message(
         }));
     }
 
-    let final_content = combined_outputs.join("\n");
-
     let components: Vec<Component> = all_buttons
         .chunks(5)
         .map(|chunk| {
@@ -128,67 +429,23 @@ pub async fn process_message(
         })
         .collect();
 
-    let bot_response = client
-        .create_message(message.channel_id)
-        .content(&final_content)
-        .components(&components)
-        .reply(message.id)
-        .allowed_mentions(Some(&AllowedMentions {
-            replied_user: false,
-            ..Default::default()
-        }))
-        .await?
-        .model()
-        .await?;
-
-    // Saves the response in the response map.
-    let response_map = ResponseMap::new(message, bot_response.id);
-    if let Err(e) = response_map.save().await {
-        tracing::warn!("Failed to save response_map due to: {:?}", e);
-    }
-
-    // Early exits if message is not in a server
-    let Some(server_config) = server_config else {
-        return Ok(());
-    };
-
-    // Removes all Sanitized emoji reactions after responding.
-    if server_config.sanitizer_mode == SanitizerMode::ManualEmote
-        || server_config.sanitizer_mode == SanitizerMode::ManualBoth
-    {
-        tracing::debug!("Removing all Sanitized emoji reactions.");
-        client
-            .delete_all_reaction(
-                message.channel_id,
-                message.id,
-                &RequestReactionType::Custom {
-                    id: crate::EMOJI_ID.get().unwrap().to_owned(),
-                    name: Some("Sanitized"),
-                },
-            )
-            .await?;
-    }
-
-    if server_config.hide_original_embed {
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-        if ResponseMap::find_match(message.id).await?.is_some()
-            && let Err(e) = client
-                .update_message(message.channel_id, message.id)
-                .flags(MessageFlags::SUPPRESS_EMBEDS)
-                .await
-        {
-            tracing::debug!("Failed to suppress embed (likely already deleted): {:?}", e);
-        }
-    }
-
-    Ok(())
+    Ok(Some(Reply {
+        content: combined_outputs.join("\n"),
+        components,
+    }))
 }
 
 /// Adds an emote to a valid message in the Sanitizer::ManualEmote/Both mode.
-pub async fn add_emote(message: &Message, client: &Client) -> anyhow::Result<()> {
+pub async fn add_emote(
+    message: &Message,
+    client: &Client,
+    server_config: &ServerConfig,
+) -> anyhow::Result<()> {
     // Exits early if URL is not valid
-    if UrlProcessor::try_new(&message.content, false).is_none() {
+    if !utils::get_links(message).iter().any(|link| {
+        UrlProcessor::try_new(link.url, false)
+            .is_some_and(|url| url.is_enabled(Some(server_config)))
+    }) {
         tracing::debug!("No valid URL found in message");
         return Ok(());
     };
@@ -206,3 +463,356 @@ pub async fn add_emote(message: &Message, client: &Client) -> anyhow::Result<()>
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::db::MemoryStore;
+    use crate::utils::testing::{self, GUILD_ID, StubRequest};
+
+    const CHANNEL_ID: u64 = 10;
+    const USER_MESSAGE_ID: u64 = 20;
+    const BOT_MESSAGE_ID: u64 = 30;
+
+    fn server_config() -> ServerConfig {
+        ServerConfig {
+            hide_original_embed: false,
+            ..ServerConfig::new(GUILD_ID)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_message_replies_and_saves_response() {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(|_| {
+            testing::json_response(&testing::message_json(BOT_MESSAGE_ID, CHANNEL_ID, 1, ""))
+        });
+        let client = testing::discord_client(addr);
+        let store = Arc::new(MemoryStore::default());
+        let message = testing::message(
+            USER_MESSAGE_ID,
+            CHANNEL_ID,
+            2,
+            "look https://x.com/someone/status/123?s=20",
+        );
+
+        process_message(&message, &client, Some(server_config()), store.as_ref())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(
+            requests[0].path,
+            format!("/api/v10/channels/{}/messages", CHANNEL_ID)
+        );
+
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(
+            body["content"],
+            "[@someone via Twitter](https://fxtwitter.com/someone/status/123)"
+        );
+        assert_eq!(
+            body["message_reference"]["message_id"],
+            USER_MESSAGE_ID.to_string()
+        );
+
+        assert_eq!(
+            store.responses(),
+            [ResponseMap {
+                user_message_id: USER_MESSAGE_ID,
+                bot_message_id: BOT_MESSAGE_ID,
+                guild_id: Some(GUILD_ID),
+                channel_id: CHANNEL_ID,
+                reposted: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_message_without_links() {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(|_| testing::empty_response());
+        let client = testing::discord_client(addr);
+        let store = Arc::new(MemoryStore::default());
+        let message = testing::message(
+            USER_MESSAGE_ID,
+            CHANNEL_ID,
+            2,
+            "`https://x.com/someone/status/123` https://example.com",
+        );
+
+        process_message(&message, &client, Some(server_config()), store.as_ref())
+            .await
+            .unwrap();
+
+        assert!(requests.lock().unwrap().is_empty());
+        assert!(store.responses().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_message_skips_disabled_platforms() {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(|_| {
+            testing::json_response(&testing::message_json(BOT_MESSAGE_ID, CHANNEL_ID, 1, ""))
+        });
+        let client = testing::discord_client(addr);
+        let store = Arc::new(MemoryStore::default());
+        let message = testing::message(
+            USER_MESSAGE_ID,
+            CHANNEL_ID,
+            2,
+            "https://x.com/someone/status/123 https://www.reddit.com/r/rust/comments/abc/title",
+        );
+
+        let mut config = server_config();
+        config.disabled_platforms.insert("reddit".to_string());
+        process_message(&message, &client, Some(config), store.as_ref())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(
+            body["content"],
+            "[@someone via Twitter](https://fxtwitter.com/someone/status/123)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_reply_leaves_out_links_that_dont_fit() {
+        testing::init_platforms();
+        let content = (0..12)
+            .map(|i| format!("https://x.com/someone/status/{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let message = testing::message(USER_MESSAGE_ID, CHANNEL_ID, 2, &content);
+
+        let config = ServerConfig {
+            reply_template: Some(format!("{{url}} {}", "a".repeat(150))),
+            ..server_config()
+        };
+        let reply = build_reply(&message, Some(&config), DELETE_BUTTON_ID)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(reply.content.chars().count() <= MAX_CONTENT_LENGTH);
+        assert_eq!(reply.content.lines().count(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_process_message_uses_reply_template() {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(|_| {
+            testing::json_response(&testing::message_json(BOT_MESSAGE_ID, CHANNEL_ID, 1, ""))
+        });
+        let client = testing::discord_client(addr);
+        let store = Arc::new(MemoryStore::default());
+        let message = testing::message(
+            USER_MESSAGE_ID,
+            CHANNEL_ID,
+            2,
+            "https://x.com/someone/status/123",
+        );
+
+        let config = ServerConfig {
+            reply_template: Some("{author} on {platform}: {url}".to_string()),
+            ..server_config()
+        };
+        process_message(&message, &client, Some(config), store.as_ref())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(
+            body["content"],
+            "someone on Twitter: https://fxtwitter.com/someone/status/123"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_message_when_disabled() {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(|_| testing::empty_response());
+        let client = testing::discord_client(addr);
+        let store = Arc::new(MemoryStore::default());
+        let message = testing::message(
+            USER_MESSAGE_ID,
+            CHANNEL_ID,
+            2,
+            "https://x.com/someone/status/123",
+        );
+
+        let config = ServerConfig {
+            sanitizer_mode: SanitizerMode::Disabled,
+            ..server_config()
+        };
+        process_message(&message, &client, Some(config), store.as_ref())
+            .await
+            .unwrap();
+
+        assert!(requests.lock().unwrap().is_empty());
+        assert!(store.responses().is_empty());
+    }
+
+    const WEBHOOK_ID: u64 = 70;
+
+    /// Discord with a regular channel that has no webhooks yet.
+    fn webhook_stub(request: &StubRequest) -> String {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", path) if path.ends_with("/webhooks") => testing::json_response(&json!([])),
+            ("GET", _) => testing::json_response(&json!({
+                "id": CHANNEL_ID.to_string(),
+                "type": 0,
+                "guild_id": GUILD_ID.to_string(),
+            })),
+            ("POST", path) if path.ends_with("/webhooks") => testing::json_response(&json!({
+                "id": WEBHOOK_ID.to_string(),
+                "type": 1,
+                "channel_id": CHANNEL_ID.to_string(),
+                "name": "Sanitizer",
+                "token": "webhook-token",
+            })),
+            ("POST", _) => testing::json_response(&testing::message_json(
+                BOT_MESSAGE_ID,
+                CHANNEL_ID,
+                WEBHOOK_ID,
+                "",
+            )),
+            _ => testing::empty_response(),
+        }
+    }
+
+    /// Reposts a message with the content, returning the requests made.
+    async fn repost(content: &str) -> (Message, Vec<StubRequest>, Arc<MemoryStore>) {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(webhook_stub);
+        let client = testing::discord_client(addr);
+        let store = Arc::new(MemoryStore::default());
+        let webhooks = WebhookCache::default();
+        let message = testing::message(USER_MESSAGE_ID, CHANNEL_ID, 2, content);
+
+        let config = ServerConfig {
+            sanitizer_mode: SanitizerMode::Repost,
+            ..server_config()
+        };
+        repost_message(&message, &client, config, store.as_ref(), &webhooks)
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap().clone();
+        (message, requests, store)
+    }
+
+    #[tokio::test]
+    async fn test_repost_message_replaces_the_message() {
+        let (message, requests, store) = repost(
+            "look ||https://x.com/someone/status/123?s=20|| [again](https://x.com/someone/status/123?s=20), https://x.com/other/status/456.",
+        )
+        .await;
+
+        let paths = requests
+            .iter()
+            .map(|request| format!("{} {}", request.method, request.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                format!("GET /api/v10/channels/{}", CHANNEL_ID),
+                format!("GET /api/v10/channels/{}/webhooks", CHANNEL_ID),
+                format!("POST /api/v10/channels/{}/webhooks", CHANNEL_ID),
+                format!(
+                    "POST /api/v10/webhooks/{}/webhook-token?wait=true&with_components=true",
+                    WEBHOOK_ID
+                ),
+                format!(
+                    "DELETE /api/v10/channels/{}/messages/{}",
+                    CHANNEL_ID, USER_MESSAGE_ID
+                ),
+            ]
+        );
+
+        let body: serde_json::Value = serde_json::from_str(&requests[3].body).unwrap();
+        assert_eq!(
+            body["content"],
+            "look ||https://fxtwitter.com/someone/status/123|| [again](https://fxtwitter.com/someone/status/123), https://fxtwitter.com/other/status/456."
+        );
+        assert_eq!(body["username"], "user2");
+        assert_eq!(
+            body["components"][0]["components"][2]["custom_id"],
+            "delete:2"
+        );
+
+        assert_eq!(
+            store.responses(),
+            [ResponseMap::repost(&message, Id::new(BOT_MESSAGE_ID))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repost_message_keeps_links_in_code() {
+        let (_, requests, _) =
+            repost("https://x.com/someone/status/123 or `https://x.com/someone/status/123`").await;
+
+        let body: serde_json::Value = serde_json::from_str(&requests[3].body).unwrap();
+        assert_eq!(
+            body["content"],
+            "https://fxtwitter.com/someone/status/123 or `https://x.com/someone/status/123`"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repost_message_replaces_only_whole_links() {
+        // The first link is a prefix of the second.
+        let (_, requests, _) =
+            repost("https://x.com/someone/status/1 https://x.com/someone/status/12?s=20").await;
+
+        let body: serde_json::Value = serde_json::from_str(&requests[3].body).unwrap();
+        assert_eq!(
+            body["content"],
+            "https://fxtwitter.com/someone/status/1 https://fxtwitter.com/someone/status/12"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_reply_deletes_when_links_are_removed() {
+        testing::init_platforms();
+        let (addr, requests) = testing::spawn_stub_server(|_| testing::empty_response());
+        let client = testing::discord_client(addr);
+        let store = Arc::new(MemoryStore::default());
+
+        let message = testing::message(USER_MESSAGE_ID, CHANNEL_ID, 2, "no link anymore");
+        let response_map = ResponseMap::new(&message, Id::new(BOT_MESSAGE_ID));
+        store.save_response(&response_map).await.unwrap();
+
+        update_reply(
+            &message,
+            &client,
+            Some(server_config()),
+            store.as_ref(),
+            response_map,
+        )
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "DELETE");
+        assert_eq!(
+            requests[0].path,
+            format!(
+                "/api/v10/channels/{}/messages/{}",
+                CHANNEL_ID, BOT_MESSAGE_ID
+            )
+        );
+        assert!(store.responses().is_empty());
+    }
+}
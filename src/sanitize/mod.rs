@@ -1,6 +1,14 @@
 mod core;
+mod health;
+mod http;
 mod messages;
+pub mod platforms;
+mod rewrite;
 
 pub use core::UrlProcessor;
 
-pub use messages::{add_emote, process_message};
+pub use health::health_worker;
+
+pub use messages::{add_emote, delete_reply, process_message, repost_message, update_reply};
+pub use platforms::PlatformRegistry;
+pub use rewrite::rewrite_message;
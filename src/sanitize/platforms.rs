@@ -0,0 +1,813 @@
+//! Registry of supported platforms, built at startup from the bundled defaults
+//! (`platforms.toml`) and an optional user supplied file set via `PLATFORMS_FILE`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Context;
+use regex::{Captures, Match, Regex, RegexSet, RegexSetBuilder};
+use serde::Deserialize;
+
+/// Platform definitions that ship with the bot.
+const BUILTIN_PLATFORMS: &str = include_str!("platforms.toml");
+
+/// Placeholders available to `label` and `fallback_label` templates.
+const LABEL_PLACEHOLDERS: &[&str] = &["author", "platform", "kind", "url"];
+
+/// The longest label a guild can set.
+pub const MAX_LABEL_LENGTH: usize = 200;
+
+/// Returns the PlatformRegistry.
+pub fn load() -> &'static PlatformRegistry {
+    crate::PLATFORM_REGISTRY
+        .get()
+        .expect("PLATFORM_REGISTRY not initialized")
+}
+
+/// Describes a single supported platform, as written in `platforms.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlatformDefinition {
+    /// Stable identifier, used to override a built-in definition from a file.
+    pub id: String,
+    pub display_name: String,
+    /// Shown under the platform in /settings, e.g. to tell apart platforms
+    /// sharing a display name.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Lowercase substrings used as a cheap pre-check before running any regex.
+    pub hosts: Vec<String>,
+    pub pattern: String,
+    /// Default fixer domain substituted into `{domain}`.
+    pub domain: String,
+    /// Vetted fixer domains guilds may pick instead of `domain`. Each must accept
+    /// the same `rewrite` path.
+    #[serde(default)]
+    pub alternative_domains: Vec<String>,
+    /// Template for the fixed url. Accepts named capture groups and `{domain}`.
+    pub rewrite: String,
+    /// Template for the output when an author is known.
+    pub label: String,
+    /// Template for the output when no author could be found.
+    #[serde(default = "default_fallback_label")]
+    pub fallback_label: String,
+    /// Capture group holding the author, if the url contains one.
+    #[serde(default)]
+    pub author_group: Option<String>,
+    /// Capture group mapped through `kinds` to produce `{kind}`.
+    #[serde(default)]
+    pub kind_group: Option<String>,
+    #[serde(default)]
+    pub kinds: HashMap<String, String>,
+    /// Query parameters kept in the fixed and original urls. A trailing `*` is a
+    /// wildcard. When set, anything not listed is dropped, otherwise everything
+    /// but `strip_params` is kept.
+    #[serde(default)]
+    pub keep_params: Vec<String>,
+    /// Tracking parameters that are always dropped, even if matched by `keep_params`.
+    #[serde(default)]
+    pub strip_params: Vec<String>,
+    /// Matches share links that only redirect to the post. These are followed so
+    /// the canonical url is used instead.
+    #[serde(default)]
+    pub share_link_pattern: Option<String>,
+    /// Network lookup used to find the author when `author_group` isn't enough.
+    #[serde(default)]
+    pub resolver: Option<AuthorResolver>,
+}
+
+fn default_fallback_label() -> String {
+    "[{kind} via {platform}]({url})".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorResolver {
+    /// Follows the short link redirect and reads the author from the `location` header.
+    TiktokRedirect,
+    /// Looks up the handle for a DID based profile path using the public Bluesky API.
+    BlueskyDid,
+    /// Reads the author from the `og:title` meta tag (`<author> - <title>`) of the
+    /// original page.
+    OgTitle,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlatformFile {
+    #[serde(default, rename = "platform")]
+    platforms: Vec<PlatformDefinition>,
+}
+
+impl PlatformFile {
+    fn parse(contents: &str, is_json: bool) -> anyhow::Result<Self> {
+        if is_json {
+            serde_json::from_str(contents).context("Invalid JSON platform file")
+        } else {
+            toml::from_str(contents).context("Invalid TOML platform file")
+        }
+    }
+}
+
+/// A platform definition along with its compiled regex.
+#[derive(Debug)]
+pub struct Platform {
+    definition: PlatformDefinition,
+    regex: Regex,
+    share_link_regex: Option<Regex>,
+}
+
+impl Platform {
+    fn compile(definition: PlatformDefinition) -> anyhow::Result<Self> {
+        let regex = Regex::new(&definition.pattern)
+            .with_context(|| format!("Invalid pattern for platform '{}'", definition.id))?;
+
+        let mut rewrite_placeholders: Vec<&str> = regex.capture_names().flatten().collect();
+        rewrite_placeholders.push("domain");
+        validate_template(&definition.rewrite, &rewrite_placeholders)
+            .with_context(|| format!("Invalid rewrite for platform '{}'", definition.id))?;
+        validate_template(&definition.label, LABEL_PLACEHOLDERS)
+            .with_context(|| format!("Invalid label for platform '{}'", definition.id))?;
+        validate_template(&definition.fallback_label, LABEL_PLACEHOLDERS)
+            .with_context(|| format!("Invalid fallback_label for platform '{}'", definition.id))?;
+
+        for group in [&definition.author_group, &definition.kind_group]
+            .into_iter()
+            .flatten()
+        {
+            if !rewrite_placeholders.contains(&group.as_str()) {
+                anyhow::bail!(
+                    "Platform '{}' references unknown capture group '{}'",
+                    definition.id,
+                    group
+                );
+            }
+        }
+
+        let share_link_regex = definition
+            .share_link_pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .with_context(|| {
+                format!(
+                    "Invalid share_link_pattern for platform '{}'",
+                    definition.id
+                )
+            })?;
+
+        Ok(Self {
+            definition,
+            regex,
+            share_link_regex,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.definition.id
+    }
+
+    pub fn display_name(&self) -> &str {
+        &self.definition.display_name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.definition.description.as_deref()
+    }
+
+    pub fn default_domain(&self) -> &str {
+        &self.definition.domain
+    }
+
+    pub fn alternative_domains(&self) -> &[String] {
+        &self.definition.alternative_domains
+    }
+
+    /// The default domain followed by the vetted alternatives.
+    pub fn domains(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.default_domain()).chain(
+            self.definition
+                .alternative_domains
+                .iter()
+                .map(String::as_str),
+        )
+    }
+
+    /// Checks if the domain is the default or one of the vetted alternatives.
+    pub fn is_vetted_domain(&self, domain: &str) -> bool {
+        self.definition.domain == domain
+            || self
+                .definition
+                .alternative_domains
+                .iter()
+                .any(|d| d == domain)
+    }
+
+    /// The vetted domains in the order they should be tried, starting with the
+    /// preferred domain (if vetted) and then the default.
+    pub fn fallback_domains<'a>(
+        &'a self,
+        preferred: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a str> {
+        let first = self.replacement_domain(preferred);
+        std::iter::once(first).chain(self.domains().filter(move |domain| *domain != first))
+    }
+
+    /// Returns the preferred domain if it is vetted, else the default domain.
+    pub fn replacement_domain<'a>(&'a self, preferred: Option<&'a str>) -> &'a str {
+        match preferred {
+            Some(domain) if self.is_vetted_domain(domain) => domain,
+            _ => self.default_domain(),
+        }
+    }
+
+    pub fn resolver(&self) -> Option<AuthorResolver> {
+        self.definition.resolver
+    }
+
+    pub fn is_share_link(&self, url: &str) -> bool {
+        self.share_link_regex
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(url))
+    }
+
+    pub fn captures<'a>(&self, input: &'a str) -> Option<Captures<'a>> {
+        self.regex.captures(input)
+    }
+
+    pub fn find<'a>(&self, input: &'a str) -> Option<Match<'a>> {
+        self.regex.find(input)
+    }
+
+    /// Builds the fixed url from the `rewrite` template using the given domain.
+    pub fn rewrite(&self, captures: &Captures, domain: &str) -> String {
+        render(&self.definition.rewrite, |name| match name {
+            "domain" => Some(domain),
+            group => captures.name(group).map(|m| m.as_str()),
+        })
+    }
+
+    /// Appends the query string following the match in the input to the url,
+    /// dropping those in `strip_params`, and those missing from `keep_params` if set.
+    pub fn with_query(&self, url: String, input: &str, match_end: usize) -> String {
+        let Some(query) = raw_query(&input[match_end..]) else {
+            return url;
+        };
+
+        let params: Vec<&str> = query
+            .split('&')
+            .filter(|param| {
+                let key = param.split('=').next().unwrap_or_default();
+                !key.is_empty()
+                    && (self.definition.keep_params.is_empty()
+                        || matches_any(&self.definition.keep_params, key))
+                    && !matches_any(&self.definition.strip_params, key)
+            })
+            .collect();
+
+        if params.is_empty() {
+            url
+        } else {
+            format!("{}?{}", url, params.join("&"))
+        }
+    }
+
+    /// Returns the author from `author_group`, if the platform has one.
+    pub fn author(&self, captures: &Captures) -> Option<String> {
+        let group = self.definition.author_group.as_deref()?;
+        captures
+            .name(group)
+            .map(|m| m.as_str().to_string())
+            .filter(|author| !author.is_empty())
+    }
+
+    /// Returns the display name for the type of post, defaulting to "Post".
+    pub fn kind(&self, captures: &Captures) -> String {
+        self.definition
+            .kind_group
+            .as_deref()
+            .and_then(|group| captures.name(group))
+            .and_then(|m| self.definition.kinds.get(&m.as_str().to_lowercase()))
+            .cloned()
+            .unwrap_or_else(|| "Post".to_string())
+    }
+
+    /// Renders the markdown output using `label`, or `fallback_label` without an author.
+    /// A guild's `custom` label is used instead, unless it needs an unknown author.
+    pub fn label(
+        &self,
+        custom: Option<&str>,
+        author: Option<&str>,
+        kind: &str,
+        url: &str,
+    ) -> String {
+        let template = match (custom, author) {
+            (Some(custom), _) if author.is_some() || !custom.contains("{author}") => custom,
+            (_, Some(_)) => &self.definition.label,
+            (_, None) => &self.definition.fallback_label,
+        };
+
+        render(template, |name| match name {
+            "author" => author,
+            "platform" => Some(self.display_name()),
+            "kind" => Some(kind),
+            "url" => Some(url),
+            _ => None,
+        })
+    }
+}
+
+/// All supported platforms, in the order they are matched.
+#[derive(Debug)]
+pub struct PlatformRegistry {
+    platforms: Vec<Platform>,
+    regex_set: RegexSet,
+}
+
+impl PlatformRegistry {
+    /// Loads the built-in platforms, then applies `PLATFORMS_FILE` if set.
+    pub fn load() -> anyhow::Result<Self> {
+        let mut definitions = Self::builtin_definitions()?;
+
+        if let Ok(path) = std::env::var("PLATFORMS_FILE") {
+            let path = Path::new(&path);
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read platform file {}", path.display()))?;
+            let is_json = path.extension().is_some_and(|ext| ext == "json");
+            let file = PlatformFile::parse(&contents, is_json)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+            tracing::info!(
+                "Loaded {} platform definitions from {}",
+                file.platforms.len(),
+                path.display()
+            );
+            merge(&mut definitions, file.platforms);
+        }
+
+        Self::from_definitions(definitions)
+    }
+
+    pub(crate) fn builtin_definitions() -> anyhow::Result<Vec<PlatformDefinition>> {
+        let file = PlatformFile::parse(BUILTIN_PLATFORMS, false)
+            .context("CRITICAL: Failed to parse built-in platforms")?;
+        Ok(file.platforms)
+    }
+
+    pub fn from_definitions(definitions: Vec<PlatformDefinition>) -> anyhow::Result<Self> {
+        let platforms = definitions
+            .into_iter()
+            .map(Platform::compile)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let regex_set = RegexSetBuilder::new(platforms.iter().map(|p| &p.definition.pattern))
+            .case_insensitive(true)
+            .multi_line(true)
+            .build()
+            .context("CRITICAL: Failed to build RegexSet")?;
+
+        Ok(Self {
+            platforms,
+            regex_set,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.platforms.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Platform> {
+        self.platforms.iter()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Platform> {
+        self.platforms.iter().find(|p| p.id() == id)
+    }
+
+    /// Returns the first platform with a url in the input.
+    pub fn detect(&self, input: &str) -> Option<&Platform> {
+        tracing::debug!("Trying to detect a match in the url.");
+        self.regex_set
+            .matches(input)
+            .iter()
+            .next()
+            .and_then(|idx| self.platforms.get(idx))
+    }
+
+    /// A simple & fast pre-check to see if a url for any platform is present.
+    pub fn contains_url(&self, input: &str) -> bool {
+        let input = input.to_lowercase();
+        self.platforms
+            .iter()
+            .flat_map(|p| &p.definition.hosts)
+            .any(|host| input.contains(host.as_str()))
+    }
+}
+
+/// Replaces definitions sharing an id with the override, and appends new ones.
+fn merge(definitions: &mut Vec<PlatformDefinition>, overrides: Vec<PlatformDefinition>) {
+    for definition in overrides {
+        match definitions.iter_mut().find(|d| d.id == definition.id) {
+            Some(existing) => *existing = definition,
+            None => definitions.push(definition),
+        }
+    }
+}
+
+/// Returns the query string (without `?` or fragment) of the url the input starts in.
+fn raw_query(rest: &str) -> Option<&str> {
+    let end = rest
+        .find(|c: char| c.is_whitespace() || matches!(c, ')' | ']' | '>' | '<' | '`' | '|'))
+        .unwrap_or(rest.len());
+    let (_, query) = rest[..end].split_once('?')?;
+    query.split('#').next()
+}
+
+/// Checks if the key matches any of the patterns, where a trailing `*` is a wildcard.
+fn matches_any(patterns: &[String], key: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => pattern == key,
+        })
+}
+
+/// Substitutes every `{name}` in the template, unknown names become empty.
+fn render<'a>(template: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        output.push_str(lookup(&rest[start + 1..start + len]).unwrap_or_default());
+        rest = &rest[start + len + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Checks a label set by a guild, which must keep the link.
+pub fn validate_label(label: &str) -> anyhow::Result<()> {
+    if label.chars().count() > MAX_LABEL_LENGTH {
+        anyhow::bail!(
+            "The format can't be longer than {} characters",
+            MAX_LABEL_LENGTH
+        );
+    }
+    validate_template(label, LABEL_PLACEHOLDERS)?;
+    if !label.contains("{url}") {
+        anyhow::bail!("The format needs a `{{url}}` placeholder for the link");
+    }
+    Ok(())
+}
+
+/// Renders a label with a sample Twitter post, to preview it.
+pub fn preview_label(label: &str) -> String {
+    render(label, |name| match name {
+        "author" => Some("someone"),
+        "platform" => Some("Twitter"),
+        "kind" => Some("Post"),
+        "url" => Some("https://fxtwitter.com/someone/status/123"),
+        _ => None,
+    })
+}
+
+/// Ensures every placeholder in the template is one of `allowed`.
+fn validate_template(template: &str, allowed: &[&str]) -> anyhow::Result<()> {
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            anyhow::bail!("Unclosed placeholder in '{}'", template);
+        };
+        let name = &rest[start + 1..start + len];
+        if !allowed.contains(&name) {
+            anyhow::bail!("Unknown placeholder '{{{}}}' in '{}'", name, template);
+        }
+        rest = &rest[start + len + 1..];
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin() -> PlatformRegistry {
+        PlatformRegistry::from_definitions(PlatformRegistry::builtin_definitions().unwrap())
+            .unwrap()
+    }
+
+    fn rewrite(registry: &PlatformRegistry, input: &str) -> Option<(String, String)> {
+        let platform = registry.detect(input)?;
+        let captures = platform.captures(input)?;
+        Some((
+            platform.id().to_string(),
+            platform.rewrite(&captures, platform.default_domain()),
+        ))
+    }
+
+    #[test]
+    fn test_builtin_platforms_load() {
+        let registry = builtin();
+        assert_eq!(registry.len(), 7);
+    }
+
+    #[test]
+    fn test_builtin_rewrites() {
+        let registry = builtin();
+
+        assert_eq!(
+            rewrite(&registry, "https://www.instagram.com/reel/abc123/?igsh=x"),
+            Some((
+                "instagram".to_string(),
+                "https://www.kkinstagram.com/reel/abc123".to_string()
+            ))
+        );
+        assert_eq!(
+            rewrite(
+                &registry,
+                "https://old.reddit.com/r/rust/comments/abc/title/"
+            ),
+            Some((
+                "reddit".to_string(),
+                "https://old.vxreddit.com/r/rust/comments/abc/title/".to_string()
+            ))
+        );
+        assert_eq!(
+            rewrite(&registry, "https://vm.tiktok.com/ZMabc/"),
+            Some((
+                "tiktok".to_string(),
+                "https://vm.kktiktok.com/ZMabc/".to_string()
+            ))
+        );
+        assert_eq!(
+            rewrite(&registry, "https://x.com/user/status/123?s=20"),
+            Some((
+                "twitter".to_string(),
+                "https://fxtwitter.com/user/status/123".to_string()
+            ))
+        );
+        assert_eq!(rewrite(&registry, "https://example.com/video"), None);
+    }
+
+    #[test]
+    fn test_bluesky_profiles() {
+        let registry = builtin();
+
+        for (input, expected, author) in [
+            (
+                "https://bsky.app/profile/alice.bsky.social/post/3kabc",
+                "https://fxbsky.app/profile/alice.bsky.social/post/3kabc",
+                Some("alice.bsky.social"),
+            ),
+            (
+                "https://bsky.app/profile/example.com/post/3kabc?ref=share",
+                "https://fxbsky.app/profile/example.com/post/3kabc",
+                Some("example.com"),
+            ),
+            (
+                "https://bsky.app/profile/did:plc:z72i7hdynmk6r22z27h6tvur/post/3kabc",
+                "https://fxbsky.app/profile/did:plc:z72i7hdynmk6r22z27h6tvur/post/3kabc",
+                None,
+            ),
+        ] {
+            let platform = registry.detect(input).unwrap();
+            let captures = platform.captures(input).unwrap();
+            assert_eq!(platform.id(), "bluesky");
+            assert_eq!(
+                platform.rewrite(&captures, platform.default_domain()),
+                expected
+            );
+            assert_eq!(platform.author(&captures).as_deref(), author);
+        }
+
+        assert!(
+            registry
+                .detect("https://bsky.app/profile/alice.bsky.social")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_replacement_domain_must_be_vetted() {
+        let registry = builtin();
+        let twitter = registry.get("twitter").unwrap();
+
+        assert_eq!(twitter.replacement_domain(None), "fxtwitter.com");
+        assert_eq!(twitter.replacement_domain(Some("fixupx.com")), "fixupx.com");
+        assert_eq!(
+            twitter.replacement_domain(Some("evil.example.com")),
+            "fxtwitter.com"
+        );
+
+        let input = "https://x.com/user/status/123";
+        let captures = twitter.captures(input).unwrap();
+        assert_eq!(
+            twitter.rewrite(&captures, twitter.replacement_domain(Some("fixupx.com"))),
+            "https://fixupx.com/user/status/123"
+        );
+    }
+
+    #[test]
+    fn test_fallback_domain_order() {
+        let registry = builtin();
+        let twitter = registry.get("twitter").unwrap();
+
+        assert_eq!(
+            twitter.fallback_domains(None).collect::<Vec<_>>(),
+            ["fxtwitter.com", "fixupx.com", "vxtwitter.com", "fixvx.com"]
+        );
+        assert_eq!(
+            twitter
+                .fallback_domains(Some("vxtwitter.com"))
+                .collect::<Vec<_>>(),
+            ["vxtwitter.com", "fxtwitter.com", "fixupx.com", "fixvx.com"]
+        );
+
+        let twitch = registry.get("twitch").unwrap();
+        assert_eq!(
+            twitch.fallback_domains(None).collect::<Vec<_>>(),
+            ["fxtwitch.seria.moe"]
+        );
+    }
+
+    #[test]
+    fn test_query_params_policy() {
+        let registry = builtin();
+
+        let with_query = |input: &str| {
+            let platform = registry.detect(input).unwrap();
+            let found = platform.find(input).unwrap();
+            platform.with_query(found.as_str().to_string(), input, found.end())
+        };
+
+        assert_eq!(
+            with_query("https://www.instagram.com/p/abc123/?img_index=2&igsh=xyz&utm_source=ig"),
+            "https://www.instagram.com/p/abc123?img_index=2"
+        );
+        assert_eq!(
+            with_query("https://x.com/user/status/123?s=20&t=abcdef"),
+            "https://x.com/user/status/123"
+        );
+        assert_eq!(
+            with_query("||https://x.com/user/status/123?s=20|| nice"),
+            "https://x.com/user/status/123"
+        );
+        assert_eq!(
+            with_query("https://www.reddit.com/r/rust/comments/abc/title/?share_id=x&context=3"),
+            "https://www.reddit.com/r/rust/comments/abc/title/?context=3"
+        );
+        assert_eq!(
+            with_query("https://www.tiktok.com/@user/video/123?_r=1&_t=8abc"),
+            "https://www.tiktok.com/@user/video/123"
+        );
+        // Without `keep_params`, only `strip_params` are dropped.
+        assert_eq!(
+            with_query("https://x.com/user/status/123?s=20&lang=en&utm_source=a"),
+            "https://x.com/user/status/123?lang=en"
+        );
+    }
+
+    #[test]
+    fn test_share_links() {
+        let registry = builtin();
+        let reddit = registry.get("reddit").unwrap();
+
+        assert!(reddit.is_share_link("https://www.reddit.com/r/rust/s/AbC123"));
+        assert!(!reddit.is_share_link("https://www.reddit.com/r/rust/comments/abc/title/"));
+        assert!(
+            !registry
+                .get("twitter")
+                .unwrap()
+                .is_share_link("https://x.com/a/status/1")
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let registry = builtin();
+
+        let input = "https://www.instagram.com/reels/abc123/";
+        let instagram = registry.detect(input).unwrap();
+        let captures = instagram.captures(input).unwrap();
+        assert_eq!(instagram.kind(&captures), "Reel");
+        assert_eq!(
+            instagram.label(None, None, &instagram.kind(&captures), "url"),
+            "[Reel via Instagram](url)"
+        );
+
+        let input = "https://x.com/user/status/123";
+        let twitter = registry.detect(input).unwrap();
+        let captures = twitter.captures(input).unwrap();
+        let author = twitter.author(&captures);
+        assert_eq!(
+            twitter.label(None, author.as_deref(), &twitter.kind(&captures), "url"),
+            "[@user via Twitter](url)"
+        );
+        assert_eq!(
+            twitter.label(None, None, "Post", "url"),
+            "[Post via Twitter](url)"
+        );
+
+        // A guild's label, which falls back to the platform's without an author.
+        let custom = Some("{platform} post by {author}: {url}");
+        assert_eq!(
+            twitter.label(custom, author.as_deref(), "Post", "url"),
+            "Twitter post by user: url"
+        );
+        assert_eq!(
+            twitter.label(custom, None, "Post", "url"),
+            "[Post via Twitter](url)"
+        );
+        assert_eq!(
+            instagram.label(Some("{kind}: {url}"), None, "Reel", "url"),
+            "Reel: url"
+        );
+    }
+
+    #[test]
+    fn test_file_overrides_and_extends_builtins() {
+        let overrides = r#"
+            [[platform]]
+            id = "twitter"
+            display_name = "X"
+            hosts = ["x.com"]
+            pattern = '(?i)https?://x\.com/(?P<username>\w+)(?P<data>/status/\d+)'
+            domain = "fixupx.com"
+            rewrite = "https://{domain}/{username}{data}"
+            label = "[@{author} via {platform}]({url})"
+            author_group = "username"
+
+            [[platform]]
+            id = "example"
+            display_name = "Example"
+            hosts = ["example.com"]
+            pattern = '(?i)https?://example\.com(?P<data>/\S+)'
+            domain = "fixexample.com"
+            rewrite = "https://{domain}{data}"
+            label = "[{kind} via {platform}]({url})"
+        "#;
+
+        let mut definitions = PlatformRegistry::builtin_definitions().unwrap();
+        let builtin_len = definitions.len();
+        merge(
+            &mut definitions,
+            PlatformFile::parse(overrides, false).unwrap().platforms,
+        );
+        let registry = PlatformRegistry::from_definitions(definitions).unwrap();
+
+        assert_eq!(registry.len(), builtin_len + 1);
+        assert!(registry.contains_url("look https://EXAMPLE.com/a"));
+        assert_eq!(
+            rewrite(&registry, "https://x.com/user/status/123"),
+            Some((
+                "twitter".to_string(),
+                "https://fixupx.com/user/status/123".to_string()
+            ))
+        );
+        assert_eq!(
+            rewrite(&registry, "https://example.com/a"),
+            Some((
+                "example".to_string(),
+                "https://fixexample.com/a".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        let file = r#"
+            [[platform]]
+            id = "broken"
+            display_name = "Broken"
+            hosts = ["broken.com"]
+            pattern = '(?i)https?://broken\.com(?P<data>/\S+)'
+            domain = "fixbroken.com"
+            rewrite = "https://{domain}{missing}"
+            label = "[{kind} via {platform}]({url})"
+        "#;
+
+        let definitions = PlatformFile::parse(file, false).unwrap().platforms;
+        assert!(PlatformRegistry::from_definitions(definitions).is_err());
+    }
+
+    #[test]
+    fn test_validate_label() {
+        assert!(validate_label("[@{author} via {platform}]({url})").is_ok());
+        assert!(validate_label("{url}").is_ok());
+        assert!(validate_label("[{author}]({link})").is_err());
+        assert!(validate_label("{author} via {platform}").is_err());
+        assert!(validate_label("{url").is_err());
+        assert!(validate_label(&format!("{{url}}{}", "a".repeat(MAX_LABEL_LENGTH))).is_err());
+
+        assert_eq!(
+            preview_label("[@{author} via {platform}]({url})"),
+            "[@someone via Twitter](https://fxtwitter.com/someone/status/123)"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Rewrites links in place, for output modes keeping the rest of the message.
+
+use std::collections::HashMap;
+
+use crate::db::ServerConfig;
+use crate::sanitize::UrlProcessor;
+use crate::utils;
+use crate::utils::markdown;
+
+/// Punctuation that ends a sentence rather than a url.
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', '!', '?', ';', ':', ')', '\'', '"'];
+
+/// Returns the content with each supported link replaced by its fixed url,
+/// for output modes keeping the rest of the message. The markdown around the
+/// links is kept, so spoilers and masked links stay intact, while links in
+/// code or suppressed with `<>` are left as they are.
+pub async fn rewrite_message(content: &str, config: &ServerConfig) -> anyhow::Result<String> {
+    let mut fixed_urls = HashMap::new();
+
+    for link in markdown::extract_links(content) {
+        if !utils::is_sanitized(&link) || fixed_urls.contains_key(link.url) {
+            continue;
+        }
+        let Some(url) = UrlProcessor::try_new(link.url, link.spoiler) else {
+            continue;
+        };
+        if !url.is_enabled(Some(config)) {
+            continue;
+        }
+
+        let Some(captures) = url.with_config(Some(config)).capture_url().await else {
+            return Err(anyhow::anyhow!("Failed to process URL"));
+        };
+        if let Some(clean_url) = captures.get_clean_url() {
+            fixed_urls.insert(link.url, clean_url.to_string());
+        }
+    }
+
+    Ok(markdown::replace_links(content, |link| {
+        if !utils::is_sanitized(link) {
+            return None;
+        }
+        let fixed_url = fixed_urls.get(link.url)?;
+
+        // Punctuation ending the sentence gets picked up as part of the link
+        // (and possibly carried over into the fixed url), it's kept after it.
+        let url = link.url.trim_end_matches(TRAILING_PUNCTUATION);
+        Some(format!(
+            "{}{}",
+            fixed_url.trim_end_matches(TRAILING_PUNCTUATION),
+            &link.url[url.len()..]
+        ))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{self, GUILD_ID};
+
+    #[tokio::test]
+    async fn test_rewrite_message_keeps_the_rest_of_the_message() {
+        testing::init_platforms();
+        let config = ServerConfig::new(GUILD_ID);
+
+        let rewritten = rewrite_message(
+            "look ||https://x.com/a/status/1?s=20|| `https://x.com/a/status/1` <https://x.com/a/status/1> [again](https://x.com/a/status/1?s=20), https://example.com https://x.com/b/status/2.",
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            rewritten,
+            "look ||https://fxtwitter.com/a/status/1|| `https://x.com/a/status/1` <https://x.com/a/status/1> [again](https://fxtwitter.com/a/status/1), https://example.com https://fxtwitter.com/b/status/2."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_message_skips_disabled_platforms() {
+        testing::init_platforms();
+        let mut config = ServerConfig::new(GUILD_ID);
+        config.disabled_platforms.insert("twitter".to_string());
+
+        let content = "https://x.com/a/status/1";
+        assert_eq!(rewrite_message(content, &config).await.unwrap(), content);
+    }
+}
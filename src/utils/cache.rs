@@ -1,33 +1,49 @@
 //! Handles in-memory caching of server configs. (This file is primarily written by an LLM)
 
+use std::collections::HashMap;
+use std::future::Future;
 use std::num::NonZeroUsize;
-use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 use dashmap::{DashMap, Entry};
 use lru::LruCache;
 
-use crate::db::ServerConfig;
+use crate::db::{ConfigChange, ConfigStore, ServerConfig};
 
-/// Returns the ConfigCache.
-pub fn load() -> &'static ConfigCache {
-    crate::CONFIG_CACHE
-        .get()
-        .expect("CONFIG_CACHE not initialized")
-}
+/// Seconds before the latest save seen that `invalidate_changed` checks again.
+/// Saves are only stamped to the second, and can arrive late from an instance
+/// with a skewed clock or whose pushes are backing off.
+const CHANGE_OVERLAP: i64 = 15 * 60;
 
-#[derive(Debug)]
+/// Caches server configs in front of the config store.
 pub struct ConfigCache {
     cache: DashMap<u64, ServerConfig>,
     lru: Mutex<LruCache<u64, ()>>,
+    store: Arc<dyn ConfigStore>,
+    /// The latest save already accounted for by `invalidate_changed`.
+    synced_until: AtomicI64,
+    /// The saves seen within `CHANGE_OVERLAP` of `synced_until`, so checking
+    /// them again doesn't drop configs that haven't changed since.
+    seen: Mutex<HashMap<u64, ConfigChange>>,
+    /// Channel id -> parent channel id, `None` for channels that aren't threads.
+    /// Only looked up in guilds with channel overrides.
+    channel_parents: Mutex<LruCache<u64, Option<u64>>>,
 }
 
 impl ConfigCache {
-    pub fn new() -> Self {
+    pub fn new(store: Arc<dyn ConfigStore>) -> Self {
         let cache_capacity =
             NonZeroUsize::new(1000).expect("Capacity must be > 0, please check source code.");
+        let channel_capacity =
+            NonZeroUsize::new(10_000).expect("Capacity must be > 0, please check source code.");
         Self {
             cache: DashMap::new(),
             lru: Mutex::new(LruCache::new(cache_capacity)),
+            store,
+            synced_until: AtomicI64::new(0),
+            seen: Mutex::new(HashMap::new()),
+            channel_parents: Mutex::new(LruCache::new(channel_capacity)),
         }
     }
 
@@ -36,31 +52,124 @@ impl ConfigCache {
         if let Some(config) = self.cache.get(&guild_id) {
             self.touch(guild_id, false);
             tracing::debug!("Found Server Config in cache");
-            return Ok(*config);
+            return Ok(config.clone());
         }
 
         tracing::debug!("Could not find guild in cache, retrieving from database.");
-        let config = ServerConfig::get_or_default(guild_id).await?;
-        self.upsert(guild_id, config, /* overwrite */ false);
+        let config = match self.store.get_config(guild_id).await? {
+            Some(config) => config,
+            None => {
+                tracing::debug!("Using default config for guild ({})", guild_id);
+                ServerConfig::new(guild_id)
+            }
+        };
+        self.upsert(guild_id, config.clone(), /* overwrite */ false);
 
         Ok(config)
     }
 
+    /// Gets the config that applies in a channel, taking channel overrides into
+    /// account. `fetch_parent` finds the parent of a thread, it's only called
+    /// when the guild has overrides and the result is cached.
+    pub async fn get_for_channel<F, Fut>(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        fetch_parent: F,
+    ) -> anyhow::Result<ServerConfig>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<Option<u64>>>,
+    {
+        let config = self.get_or_fetch(guild_id).await?;
+        if config.channel_overrides.is_empty() || config.channel_overrides.contains_key(&channel_id)
+        {
+            return Ok(config.for_channel(channel_id, None));
+        }
+
+        let cached = self.channel_parents().get(&channel_id).copied();
+        let parent_id = match cached {
+            Some(parent_id) => parent_id,
+            None => {
+                let parent_id = fetch_parent().await?;
+                self.channel_parents().put(channel_id, parent_id);
+                parent_id
+            }
+        };
+
+        Ok(config.for_channel(channel_id, parent_id))
+    }
+
+    /// Forgets a deleted channel (or thread).
+    pub fn forget_channel(&self, channel_id: u64) {
+        self.channel_parents().pop(&channel_id);
+    }
+
+    fn channel_parents(&self) -> MutexGuard<'_, LruCache<u64, Option<u64>>> {
+        self.channel_parents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     // Update the server config in the database and cache.
     pub async fn update_config(&self, guild_id: u64, config: ServerConfig) -> anyhow::Result<()> {
         // Updates the database with change.
-        config.save().await?;
+        self.store.save_config(&config).await?;
         // Updates the cache
         self.upsert(guild_id, config, /* overwrite */ true);
 
         Ok(())
     }
 
+    /// Drops the cached configs of guilds saved elsewhere since the last check
+    /// (e.g. by another instance, and brought in by a pull), so they get
+    /// fetched again. Saves within `CHANGE_OVERLAP` of the latest one seen are
+    /// checked again, and only count when they differ from what was seen.
+    pub async fn invalidate_changed(&self) -> anyhow::Result<()> {
+        let since = self.synced_until.load(Ordering::Relaxed) - CHANGE_OVERLAP;
+        let changed = self.store.changed_since(since).await?;
+
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut invalidated = 0;
+        for change in &changed {
+            if seen.insert(change.guild_id, *change) != Some(*change) {
+                self.remove(change.guild_id);
+                invalidated += 1;
+            }
+        }
+
+        if let Some(latest) = changed.iter().map(|change| change.updated_at).max() {
+            let synced_until = self.synced_until.fetch_max(latest, Ordering::Relaxed);
+            let since = synced_until.max(latest) - CHANGE_OVERLAP;
+            seen.retain(|_, change| change.updated_at >= since);
+        }
+
+        if invalidated > 0 {
+            tracing::debug!("Invalidated {} changed server configs", invalidated);
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, guild_id: u64) {
+        if self.cache.remove(&guild_id).is_none() {
+            return;
+        }
+
+        match self.lru.lock() {
+            Ok(mut lru) => {
+                lru.pop(&guild_id);
+            }
+            Err(e) => self.handle_poison(e, guild_id),
+        }
+    }
+
     /// Inserts or updates `guild_id` in the cache, atomically via DashMap's
     /// per-shard entry API (so concurrent callers can't double-insert or
     /// race the eviction count). If the key is already present:
     ///   - `overwrite = true`  -> replaces the stored value (explicit updates)
     ///   - `overwrite = false` -> leaves the existing value, just promotes it
+    ///
     /// New keys are always inserted and pushed into the LRU, evicting the
     /// least-recently-used entry if that puts the cache over capacity.
     fn upsert(&self, guild_id: u64, config: ServerConfig, overwrite: bool) {
@@ -112,3 +221,158 @@ impl ConfigCache {
         tracing::warn!("Cache and LRU cleared and reset after poison recovery.");
     }
 }
+
+/// Remembers the latest edit handled for recently edited messages. Once a
+/// message is edited, embeds resolving and flag changes fire updates carrying
+/// the same edit again.
+pub struct EditCache {
+    edits: Mutex<LruCache<u64, i64>>,
+}
+
+impl Default for EditCache {
+    fn default() -> Self {
+        let capacity =
+            NonZeroUsize::new(1000).expect("Capacity must be > 0, please check source code.");
+        Self {
+            edits: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+impl EditCache {
+    /// Records the edit of a message (by its edit timestamp), returning whether
+    /// it wasn't handled before.
+    pub fn is_new_edit(&self, message_id: u64, edited_at: i64) -> bool {
+        let mut edits = self.edits.lock().unwrap_or_else(PoisonError::into_inner);
+        match edits.get(&message_id) {
+            Some(&handled_at) if handled_at >= edited_at => false,
+            _ => {
+                edits.put(message_id, edited_at);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{ChannelConfig, MemoryStore};
+    use crate::discord::models::SanitizerMode;
+
+    #[test]
+    fn test_edit_cache_only_reports_new_edits() {
+        let edits = EditCache::default();
+
+        assert!(edits.is_new_edit(1, 100));
+        // An embed resolving for the same edit.
+        assert!(!edits.is_new_edit(1, 100));
+        assert!(edits.is_new_edit(1, 200));
+        assert!(edits.is_new_edit(2, 100));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_changed_refetches_configs_saved_elsewhere() {
+        let store = Arc::new(MemoryStore::default());
+        let cache = ConfigCache::new(store.clone());
+
+        let mut config = cache.get_or_fetch(1).await.unwrap();
+        assert_eq!(config.sanitizer_mode, SanitizerMode::default());
+
+        // Saved by another instance sharing the store.
+        config.sanitizer_mode = SanitizerMode::ManualEmote;
+        store.save_config(&config).await.unwrap();
+        assert_eq!(
+            cache.get_or_fetch(1).await.unwrap().sanitizer_mode,
+            SanitizerMode::default()
+        );
+
+        cache.invalidate_changed().await.unwrap();
+        assert_eq!(
+            cache.get_or_fetch(1).await.unwrap().sanitizer_mode,
+            SanitizerMode::ManualEmote
+        );
+
+        // Nothing changed since, so the cached config stays.
+        cache.invalidate_changed().await.unwrap();
+        assert!(cache.cache.contains_key(&1));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_changed_catches_saves_in_the_same_second() {
+        let store = Arc::new(MemoryStore::default());
+        let cache = ConfigCache::new(store.clone());
+
+        let mut config = ServerConfig::new(1);
+        store.save_config_at(&config, 100);
+        cache.get_or_fetch(1).await.unwrap();
+        cache.invalidate_changed().await.unwrap();
+
+        // Saved again elsewhere, within the same second.
+        config.sanitizer_mode = SanitizerMode::ManualEmote;
+        store.save_config_at(&config, 100);
+        cache.get_or_fetch(1).await.unwrap();
+        cache.invalidate_changed().await.unwrap();
+
+        assert_eq!(
+            cache.get_or_fetch(1).await.unwrap().sanitizer_mode,
+            SanitizerMode::ManualEmote
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_changed_catches_late_saves() {
+        let store = Arc::new(MemoryStore::default());
+        let cache = ConfigCache::new(store.clone());
+
+        store.save_config_at(&ServerConfig::new(2), 1000);
+        cache.invalidate_changed().await.unwrap();
+
+        let mut config = cache.get_or_fetch(1).await.unwrap();
+
+        // Pushed late by an instance, stamped before the latest save seen.
+        config.sanitizer_mode = SanitizerMode::ManualEmote;
+        store.save_config_at(&config, 990);
+        cache.invalidate_changed().await.unwrap();
+
+        assert_eq!(
+            cache.get_or_fetch(1).await.unwrap().sanitizer_mode,
+            SanitizerMode::ManualEmote
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_for_channel_looks_up_thread_parents_once() {
+        let cache = ConfigCache::new(Arc::new(MemoryStore::default()));
+
+        // Without overrides there's no need to know the parent.
+        let config = cache
+            .get_for_channel(1, 11, || async { panic!("parent looked up") })
+            .await
+            .unwrap();
+        assert_eq!(config.sanitizer_mode, SanitizerMode::default());
+
+        let mut config = ServerConfig::new(1);
+        config.channel_overrides.insert(
+            10,
+            ChannelConfig {
+                sanitizer_mode: Some(SanitizerMode::ManualEmote),
+                ..Default::default()
+            },
+        );
+        cache.update_config(1, config).await.unwrap();
+
+        let thread = cache
+            .get_for_channel(1, 11, || async { Ok(Some(10)) })
+            .await
+            .unwrap();
+        assert_eq!(thread.sanitizer_mode, SanitizerMode::ManualEmote);
+
+        // The parent is cached.
+        let thread = cache
+            .get_for_channel(1, 11, || async { panic!("parent looked up again") })
+            .await
+            .unwrap();
+        assert_eq!(thread.sanitizer_mode, SanitizerMode::ManualEmote);
+    }
+}
@@ -3,6 +3,8 @@ use twilight_model::channel::Message;
 use twilight_model::channel::message::MessageFlags;
 
 use crate::BOT_USER_ID;
+use crate::sanitize::platforms;
+use crate::utils::markdown::{self, LinkSpan};
 
 /// Unsupresses a message's embed.
 pub async fn unsupress_embeds(message: &Message, client: &Client) -> anyhow::Result<()> {
@@ -29,24 +31,24 @@ pub fn is_bot_mentioned(message: &Message) -> bool {
 
 /// A simple & fast pre-check to see if a url is present.
 pub fn contains_url(input: &str) -> bool {
-    let input = input.to_lowercase();
-    input.contains("instagram.com")
-        || input.contains("reddit.com")
-        || input.contains("tiktok.com")
-        // || input.contains("twitch.tv")
-        || input.contains("twitter.com")
-        || input.contains("x.com")
+    platforms::load().contains_url(input)
 }
 
-/// Iterates over the input to return a list of links.
-pub fn get_links(msg: &Message) -> Vec<&str> {
-    msg.content
-        .split_whitespace()
-        .filter(|word| contains_url(word))
-        .fold(Vec::new(), |mut unique, word| {
-            if !unique.contains(&word) {
-                unique.push(word);
+/// Returns the unique links in a message that should be sanitized, skipping
+/// links in code and links whose embed the author suppressed with `<...>`.
+pub fn get_links(msg: &Message) -> Vec<LinkSpan<'_>> {
+    markdown::extract_links(&msg.content)
+        .into_iter()
+        .filter(is_sanitized)
+        .fold(Vec::new(), |mut unique: Vec<LinkSpan>, link| {
+            if !unique.iter().any(|l| l.url == link.url) {
+                unique.push(link);
             }
             unique
         })
 }
+
+/// Whether a link is one to sanitize, see `get_links`.
+pub fn is_sanitized(link: &LinkSpan) -> bool {
+    !link.in_code && !link.suppressed && contains_url(link.url)
+}
@@ -0,0 +1,264 @@
+//! A small Discord markdown tokenizer, only concerned with where links appear.
+
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static URL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)https?://[^\s<>|`]+").expect("Valid URL regex"));
+
+/// A link found in a message, along with the markdown surrounding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkSpan<'a> {
+    pub url: &'a str,
+    /// Inside an inline code span or a fenced code block.
+    pub in_code: bool,
+    /// Wrapped in `<...>` to suppress its embed.
+    pub suppressed: bool,
+    /// The target of a masked link, `[text](url)`.
+    pub masked: bool,
+    /// Inside a `||spoiler||`.
+    pub spoiler: bool,
+}
+
+/// Returns every link in the content, in order of appearance.
+pub fn extract_links(content: &str) -> Vec<LinkSpan<'_>> {
+    link_spans(content)
+        .into_iter()
+        .map(|(_, link)| link)
+        .collect()
+}
+
+/// Returns the content with each link `replace` gives a replacement for swapped
+/// out, leaving the text and markdown around the links untouched.
+pub fn replace_links<'a, F>(content: &'a str, mut replace: F) -> String
+where
+    F: FnMut(&LinkSpan<'a>) -> Option<String>,
+{
+    let mut output = String::with_capacity(content.len());
+    let mut copied_until = 0;
+
+    for (start, link) in link_spans(content) {
+        if let Some(replacement) = replace(&link) {
+            output.push_str(&content[copied_until..start]);
+            output.push_str(&replacement);
+            copied_until = start + link.url.len();
+        }
+    }
+
+    output.push_str(&content[copied_until..]);
+    output
+}
+
+/// Every link in the content along with the byte offset it starts at.
+fn link_spans(content: &str) -> Vec<(usize, LinkSpan<'_>)> {
+    let code = code_ranges(content);
+    let spoilers = spoiler_ranges(content, &code);
+    let within = |ranges: &[Range<usize>], idx: usize| ranges.iter().any(|r| r.contains(&idx));
+
+    URL_REGEX
+        .find_iter(content)
+        .map(|found| {
+            let before = &content[..found.start()];
+            let mut url = found.as_str();
+
+            let suppressed = before.ends_with('<') && content[found.end()..].starts_with('>');
+            let masked = before.ends_with("](") || before.ends_with("](<");
+            if masked {
+                // The url of a masked link ends at the closing parenthesis.
+                url = url.split(')').next().unwrap_or(url);
+            }
+
+            let link = LinkSpan {
+                url,
+                in_code: within(&code, found.start()),
+                suppressed,
+                masked,
+                spoiler: within(&spoilers, found.start()),
+            };
+            (found.start(), link)
+        })
+        .collect()
+}
+
+/// Byte ranges covered by inline code spans and fenced code blocks. A run of
+/// backticks is only closed by a run of the same length.
+fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    let bytes = content.as_bytes();
+    let mut ranges = Vec::new();
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] != b'`' {
+            idx += 1;
+            continue;
+        }
+
+        let run = bytes[idx..].iter().take_while(|&&b| b == b'`').count();
+        let fence = &content[idx..idx + run];
+        let mut search = idx + run;
+        let mut closed = None;
+
+        while let Some(offset) = content[search..].find(fence) {
+            let candidate = search + offset;
+            let candidate_run = bytes[candidate..]
+                .iter()
+                .take_while(|&&b| b == b'`')
+                .count();
+            if candidate_run == run {
+                closed = Some(candidate);
+                break;
+            }
+            search = candidate + candidate_run;
+        }
+
+        match closed {
+            Some(end) => {
+                ranges.push(idx..end + run);
+                idx = end + run;
+            }
+            None => idx += run,
+        }
+    }
+
+    ranges
+}
+
+/// Byte ranges between paired `||` markers outside of code. An unpaired
+/// trailing marker is shown literally by Discord, so it's ignored.
+fn spoiler_ranges(content: &str, code: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut open = None;
+    let mut search = 0;
+
+    while let Some(offset) = content[search..].find("||") {
+        let idx = search + offset;
+        search = idx + 2;
+
+        if code.iter().any(|r| r.contains(&idx)) {
+            continue;
+        }
+
+        match open.take() {
+            Some(start) => ranges.push(start..idx),
+            None => open = Some(idx + 2),
+        }
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str) -> LinkSpan<'_> {
+        LinkSpan {
+            url,
+            in_code: false,
+            suppressed: false,
+            masked: false,
+            spoiler: false,
+        }
+    }
+
+    #[test]
+    fn test_plain_links() {
+        assert_eq!(
+            extract_links("look https://x.com/a/status/1 and https://x.com/b/status/2!"),
+            [
+                link("https://x.com/a/status/1"),
+                link("https://x.com/b/status/2!")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_links() {
+        let content = "`https://x.com/a/status/1` ``https://x.com/b/status/2``\n```\nhttps://x.com/c/status/3\n```";
+        let links = extract_links(content);
+
+        assert_eq!(links.len(), 3);
+        assert!(links.iter().all(|link| link.in_code));
+
+        let unclosed = extract_links("` https://x.com/a/status/1");
+        assert!(!unclosed[0].in_code);
+    }
+
+    #[test]
+    fn test_suppressed_and_masked_links() {
+        let links = extract_links(
+            "<https://x.com/a/status/1> [tweet](https://x.com/b/status/2) [hidden](<https://x.com/c/status/3>)",
+        );
+
+        assert_eq!(
+            links,
+            [
+                LinkSpan {
+                    suppressed: true,
+                    ..link("https://x.com/a/status/1")
+                },
+                LinkSpan {
+                    masked: true,
+                    ..link("https://x.com/b/status/2")
+                },
+                LinkSpan {
+                    suppressed: true,
+                    masked: true,
+                    ..link("https://x.com/c/status/3")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spoiler_links() {
+        let links = extract_links(
+            "||https://x.com/a/status/1|| || look https://x.com/b/status/2 || https://x.com/c/status/3 ||",
+        );
+
+        assert_eq!(
+            links,
+            [
+                LinkSpan {
+                    spoiler: true,
+                    ..link("https://x.com/a/status/1")
+                },
+                LinkSpan {
+                    spoiler: true,
+                    ..link("https://x.com/b/status/2")
+                },
+                link("https://x.com/c/status/3"),
+            ]
+        );
+
+        // Spoilers can span multiple lines, each link is checked on its own.
+        let links = extract_links(
+            "||first https://x.com/a/status/1\nsecond https://x.com/b/status/2||\nhttps://x.com/c/status/3",
+        );
+        assert_eq!(
+            links.iter().map(|link| link.spoiler).collect::<Vec<_>>(),
+            [true, true, false]
+        );
+
+        // Pipes inside code don't open a spoiler.
+        let links = extract_links("`||` https://x.com/a/status/1 ||");
+        assert!(!links[0].spoiler);
+    }
+
+    #[test]
+    fn test_replace_links() {
+        let content = "||https://x.com/a/status/1|| `https://x.com/a/status/1` [tweet](https://x.com/a/status/1) <https://x.com/b/status/2>";
+        let replaced = replace_links(content, |link| {
+            (!link.in_code).then(|| link.url.replace("x.com", "fxtwitter.com"))
+        });
+
+        assert_eq!(
+            replaced,
+            "||https://fxtwitter.com/a/status/1|| `https://x.com/a/status/1` [tweet](https://fxtwitter.com/a/status/1) <https://fxtwitter.com/b/status/2>"
+        );
+
+        assert_eq!(replace_links(content, |_| None), content);
+    }
+}
@@ -1,4 +1,7 @@
 pub mod cache;
 mod helper;
+pub mod markdown;
+#[cfg(test)]
+pub mod testing;
 
-pub use helper::{contains_url, get_links, is_bot_mentioned, unsupress_embeds};
+pub use helper::{contains_url, get_links, is_bot_mentioned, is_sanitized, unsupress_embeds};
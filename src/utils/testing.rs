@@ -0,0 +1,157 @@
+//! Helpers shared by tests: a stub http server standing in for Discord (or any
+//! other site), and fixtures for the payloads it deals in.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{Value, json};
+use twilight_http::Client;
+use twilight_model::channel::Message;
+
+use crate::sanitize::PlatformRegistry;
+
+/// The guild every fixture is in.
+pub const GUILD_ID: u64 = 1000;
+
+/// A request received by the stub server.
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+/// Every request received so far, in order.
+pub type StubRequests = Arc<Mutex<Vec<StubRequest>>>;
+
+/// Serves plain http, answering each request with the raw response returned by
+/// the handler and recording it.
+pub fn spawn_stub_server<F>(handler: F) -> (SocketAddr, StubRequests)
+where
+    F: Fn(&StubRequest) -> String + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = StubRequests::default();
+
+    let recorded = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let Some(request) = read_request(&mut reader) else {
+                continue;
+            };
+
+            let response = handler(&request);
+            recorded.lock().unwrap().push(request);
+            let _ = (&stream).write_all(response.as_bytes());
+        }
+    });
+
+    (addr, requests)
+}
+
+fn read_request(reader: &mut impl BufRead) -> Option<StubRequest> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().ok()?;
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(StubRequest {
+        method,
+        path,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+/// A raw http response with a body.
+pub fn http_response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// A raw http response with a json body.
+pub fn json_response(body: &Value) -> String {
+    http_response("200 OK", "application/json", &body.to_string())
+}
+
+/// A raw http response with no body.
+pub fn empty_response() -> String {
+    "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n".to_string()
+}
+
+/// A Discord client sending every request to the stub server.
+pub fn discord_client(addr: SocketAddr) -> Client {
+    Client::builder()
+        .proxy(addr.to_string(), true)
+        .ratelimiter(None)
+        .token("Bot test".to_string())
+        .build()
+}
+
+/// Loads the built-in platforms, for code that reads the global registry.
+pub fn init_platforms() {
+    crate::PLATFORM_REGISTRY.get_or_init(|| {
+        PlatformRegistry::from_definitions(PlatformRegistry::builtin_definitions().unwrap())
+            .unwrap()
+    });
+}
+
+pub fn user_json(id: u64) -> Value {
+    json!({
+        "id": id.to_string(),
+        "username": format!("user{}", id),
+        "discriminator": "0",
+        "avatar": null,
+    })
+}
+
+/// A message payload as sent by Discord.
+pub fn message_json(id: u64, channel_id: u64, author_id: u64, content: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "guild_id": GUILD_ID.to_string(),
+        "author": user_json(author_id),
+        "content": content,
+        "timestamp": "2026-01-01T00:00:00.000000+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "pinned": false,
+        "type": 0,
+        "flags": 0,
+    })
+}
+
+pub fn message(id: u64, channel_id: u64, author_id: u64, content: &str) -> Message {
+    serde_json::from_value(message_json(id, channel_id, author_id, content)).unwrap()
+}